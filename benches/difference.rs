@@ -0,0 +1,44 @@
+//! Сравнение реализаций разницы кадров на 1080p и 4K.
+//!
+//! Запуск: `cargo bench --bench difference`
+
+use std::hint::black_box;
+use std::time::Instant;
+use video_derivative::{DifferenceBackend, FrameGenerator, ProcessingConfig, VideoDerivativeProcessor};
+
+const ITERATIONS: usize = 20;
+
+fn main() {
+    let resolutions = [("1080p", 1920, 1080), ("4K", 3840, 2160)];
+    let backends = [
+        DifferenceBackend::PerPixel,
+        DifferenceBackend::Slice,
+        DifferenceBackend::Parallel,
+    ];
+
+    for (name, width, height) in resolutions {
+        let frames = FrameGenerator::new(width, height, 30).generate_complex_scene_frames(2);
+
+        for backend in backends {
+            let config = ProcessingConfig { backend, ..ProcessingConfig::default() };
+            let mut processor = VideoDerivativeProcessor::new(config);
+            processor.process_frame(&frames[0]);
+
+            let start = Instant::now();
+            for i in 0..ITERATIONS {
+                black_box(processor.process_frame(&frames[(i + 1) % 2]));
+            }
+            let elapsed = start.elapsed().as_secs_f64();
+
+            let per_frame_ms = elapsed * 1000.0 / ITERATIONS as f64;
+            let megapixels = (width * height) as f64 * ITERATIONS as f64 / elapsed / 1e6;
+            println!(
+                "{:>5} {:<10} {:>8.2} мс/кадр {:>9.1} Мпикс/с",
+                name,
+                format!("{:?}", backend),
+                per_frame_ms,
+                megapixels
+            );
+        }
+    }
+}
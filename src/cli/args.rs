@@ -0,0 +1,612 @@
+//! Описание командной строки
+
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use video_derivative::processors::MotionEventDetector;
+use video_derivative::types::{CropRect, GeometryStage, ResizeFilter};
+use video_derivative::video::{parse_time, AudioMode, EncoderConfig, EncoderPixelFormat, FrameRange, VideoCodec};
+use video_derivative::{CompositeLayout, ConfigFile, ConfigFormat, DifferenceBackend, ProcessingConfig, ResizeMode, TimeNormalization};
+
+/// Порог шумоподавления CLI по умолчанию (мягче библиотечного)
+pub const DEFAULT_THRESHOLD: u8 = 25;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "video_deivative_cli",
+    version,
+    about = "Производные видео: разница соседних кадров, анализ движения и сравнение роликов"
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub verbosity: Verbosity,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Построить производное видео (по умолчанию, если подкоманда не указана)
+    Derive(DeriveArgs),
+    /// Покадровая статистика движения в JSON Lines
+    Analyze(AnalyzeArgs),
+    /// Начала и концы движения в JSON Lines
+    Events(EventsArgs),
+    /// Сведения о файле: контейнер, видео- и аудиопотоки
+    Probe(ProbeArgs),
+    /// Синтетическое тестовое видео
+    Generate(GenerateArgs),
+    /// Сравнить два видео кадр за кадром (код выхода 3, если различаются)
+    Compare(CompareArgs),
+    /// Производные многих видео параллельно, с пропуском готовых и отчетом
+    Batch(BatchArgs),
+}
+
+impl Command {
+    /// Имена подкоманд - для совместимости со старым вызовом `cli <видео> [параметры]`
+    pub const NAMES: [&'static str; 8] =
+        ["derive", "analyze", "events", "probe", "generate", "compare", "batch", "help"];
+}
+
+/// Подробность вывода. Сообщения идут в stderr, данные - в stdout.
+#[derive(Args, Debug, Clone, Copy)]
+pub struct Verbosity {
+    /// Подробнее: параметры обработки и сведения о входе
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Только ошибки: без полосы прогресса и сообщений
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DeriveArgs {
+    /// Видео, Y4M, директория или шаблон изображений (`shots/*.tiff`); `-` - stdin в режиме --live
+    pub input: String,
+
+    /// Видео, .gif/.apng/.webp, директория или out/%06d.png; `-` - Y4M в stdout в режиме --live
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Раскладка выходного видео (по умолчанию derivative)
+    #[arg(long, value_name = "derivative|side-by-side|stacked|grid|overlay")]
+    pub layout: Option<CompositeLayout>,
+
+    /// Рамки, номера треков и траектории движения
+    #[arg(long)]
+    pub annotate: bool,
+
+    /// Звук исходного видео (по умолчанию none)
+    #[arg(long, value_name = "copy|aac|none|КОДЕК:БИТРЕЙТ")]
+    pub audio: Option<AudioMode>,
+
+    /// Сохранять длительности кадров видео с переменной частотой
+    /// (кадры кодируются через временные PNG, медленнее)
+    #[arg(long)]
+    pub preserve_timestamps: bool,
+
+    #[command(flatten)]
+    pub processing: ProcessingArgs,
+
+    #[command(flatten)]
+    pub range: RangeArgs,
+
+    #[command(flatten)]
+    pub encoder: EncoderArgs,
+
+    #[command(flatten)]
+    pub live: LiveArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+impl DeriveArgs {
+    /// Конфигурация из `--config` с параметрами командной строки поверх нее
+    pub fn config(&self) -> video_derivative::Result<ConfigFile> {
+        self.config.with_output(&self.processing, &self.encoder, self.layout, self.annotate, self.audio.clone())
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// Видео, Y4M, директория или шаблон изображений
+    pub input: String,
+
+    /// Файл для статистики (по умолчанию stdout)
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Только итог одной строкой JSON, без покадровой статистики
+    #[arg(long)]
+    pub summary_only: bool,
+
+    /// Доля изменившихся пикселей, с которой кадр считается кадром с движением
+    #[arg(long, default_value_t = 0.01, value_name = "ДОЛЯ")]
+    pub motion_fraction: f32,
+
+    #[command(flatten)]
+    pub processing: ProcessingArgs,
+
+    #[command(flatten)]
+    pub range: RangeArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct EventsArgs {
+    /// Видео, Y4M, директория или шаблон изображений
+    pub input: String,
+
+    /// Файл для событий (по умолчанию stdout)
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[command(flatten)]
+    pub detector: DetectorArgs,
+
+    #[command(flatten)]
+    pub processing: ProcessingArgs,
+
+    #[command(flatten)]
+    pub range: RangeArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ProbeArgs {
+    pub input: String,
+
+    /// Вывод в JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    /// Выходной файл: видео, .y4m, .gif/.apng или out/%06d.png
+    pub output: String,
+
+    #[arg(long, default_value_t = 640)]
+    pub width: u32,
+
+    #[arg(long, default_value_t = 480)]
+    pub height: u32,
+
+    #[arg(long, default_value_t = 30)]
+    pub fps: u32,
+
+    /// Количество кадров (все кадры создаются в памяти)
+    #[arg(long, default_value_t = 90)]
+    pub frames: usize,
+
+    /// Сцена
+    #[arg(long, value_enum, default_value_t = Scene::Moving)]
+    pub scene: Scene,
+
+    #[command(flatten)]
+    pub encoder: EncoderArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    /// Белый квадрат на спокойном фоне с редким шумом
+    Moving,
+    /// Два объекта на градиенте
+    Complex,
+}
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    pub first: String,
+    pub second: String,
+
+    /// Частота кадров для последовательностей изображений
+    #[arg(long)]
+    pub fps: Option<u32>,
+
+    /// Пиксель отличается, если компонента расходится больше чем на столько
+    #[arg(long, default_value_t = 0)]
+    pub tolerance: u8,
+
+    /// Считать видео совпадающими, если PSNR каждого кадра не ниже (дБ)
+    #[arg(long, value_name = "ДБ")]
+    pub min_psnr: Option<f64>,
+
+    /// Покадровое сравнение в JSON Lines
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[command(flatten)]
+    pub range: RangeArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Директории (рекурсивно), шаблоны (`clips/*.mp4`) или видеофайлы
+    #[arg(required_unless_present = "manifest")]
+    pub inputs: Vec<String>,
+
+    /// Список входов: путь, директория или шаблон в каждой строке, `#` - комментарий
+    #[arg(long, value_name = "ФАЙЛ")]
+    pub manifest: Option<PathBuf>,
+
+    /// Директория результатов; структура входных директорий в ней повторяется
+    #[arg(short, long, value_name = "ДИР")]
+    pub output_dir: PathBuf,
+
+    /// Сколько видео обрабатывать одновременно (по умолчанию число ядер)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Обработать заново и те видео, для которых результат уже есть
+    #[arg(long)]
+    pub force: bool,
+
+    /// Отчет в JSON: итоги и состояние каждого видео (`-` - stdout)
+    #[arg(long, value_name = "путь|-")]
+    pub report: Option<String>,
+
+    /// Раскладка выходного видео (по умолчанию derivative)
+    #[arg(long, value_name = "derivative|side-by-side|stacked|grid|overlay")]
+    pub layout: Option<CompositeLayout>,
+
+    /// Рамки, номера треков и траектории движения
+    #[arg(long)]
+    pub annotate: bool,
+
+    /// Звук исходного видео (по умолчанию none)
+    #[arg(long, value_name = "copy|aac|none|КОДЕК:БИТРЕЙТ")]
+    pub audio: Option<AudioMode>,
+
+    #[command(flatten)]
+    pub processing: ProcessingArgs,
+
+    #[command(flatten)]
+    pub range: RangeArgs,
+
+    #[command(flatten)]
+    pub encoder: EncoderArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+impl BatchArgs {
+    /// Одна конфигурация на все видео пакета
+    pub fn config(&self) -> video_derivative::Result<ConfigFile> {
+        self.config.with_output(&self.processing, &self.encoder, self.layout, self.annotate, self.audio.clone())
+    }
+}
+
+/// Поля `ProcessingConfig`; заданные перекрывают значения из `--config`
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Обработка")]
+pub struct ProcessingArgs {
+    /// Частота кадров (обязательна для изображений; для видео - из файла)
+    #[arg(long)]
+    pub fps: Option<u32>,
+
+    /// Порог шумоподавления 0..255 (по умолчанию 25)
+    #[arg(long)]
+    pub threshold: Option<u8>,
+
+    /// Обнулять разницы ниже порога (включено по умолчанию; перекрывает --config)
+    #[arg(long, overrides_with = "no_noise_reduction")]
+    pub noise_reduction: bool,
+
+    /// Не обнулять разницы ниже порога
+    #[arg(long, overrides_with = "noise_reduction")]
+    pub no_noise_reduction: bool,
+
+    /// Период опорных кадров знаковых производных (0 - только первый)
+    #[arg(long, value_name = "N")]
+    pub keyframe_interval: Option<usize>,
+
+    /// Способ вычисления разницы
+    #[arg(long, value_name = "per-pixel|slice|parallel")]
+    pub backend: Option<DifferenceBackend>,
+
+    /// Делить разницу на интервал между кадрами и умножать на SCALE (1/30 для 30 к/с)
+    #[arg(long, value_name = "SCALE")]
+    pub normalize_time: Option<f32>,
+
+    /// Размер выхода (по умолчанию вписывается с --resize fit)
+    #[arg(long, value_name = "ШxВ", value_parser = parse_size)]
+    pub size: Option<(u32, u32)>,
+
+    /// Режим масштабирования до --size
+    #[arg(long, value_name = "none|fit|fill|stretch|pad")]
+    pub resize: Option<ResizeMode>,
+
+    /// Фильтр масштабирования
+    #[arg(long, value_name = "nearest|bilinear|bicubic|gaussian|lanczos")]
+    pub filter: Option<ResizeFilter>,
+
+    /// Обрезка исходного кадра
+    #[arg(long, value_name = "ш:в[:x:y]")]
+    pub crop: Option<CropRect>,
+
+    /// Масштабировать до разницы (быстрее) или после нее
+    #[arg(long, value_name = "before|after")]
+    pub resize_stage: Option<GeometryStage>,
+}
+
+impl ProcessingArgs {
+    pub fn apply(&self, mut config: ProcessingConfig) -> ProcessingConfig {
+        if let Some(threshold) = self.threshold {
+            config.threshold = threshold;
+        }
+        // Из пары флагов действует последний
+        if self.noise_reduction {
+            config.noise_reduction = true;
+        } else if self.no_noise_reduction {
+            config.noise_reduction = false;
+        }
+        if let Some(fps) = self.fps {
+            config.fps = fps;
+        }
+        if let Some((width, height)) = self.size {
+            config.output_width = width;
+            config.output_height = height;
+            // Размер без режима - вписываем с сохранением пропорций
+            if config.geometry.resize == ResizeMode::None {
+                config.geometry.resize = ResizeMode::Fit;
+            }
+        }
+        if let Some(interval) = self.keyframe_interval {
+            config.keyframe_interval = interval;
+        }
+        if let Some(backend) = self.backend {
+            config.backend = backend;
+        }
+        if let Some(scale) = self.normalize_time {
+            config.time_normalization = TimeNormalization::PerSecond { scale };
+        }
+        if let Some(resize) = self.resize {
+            config.geometry.resize = resize;
+        }
+        if let Some(filter) = self.filter {
+            config.geometry.filter = filter;
+        }
+        config.geometry.crop = self.crop.or(config.geometry.crop);
+        if let Some(stage) = self.resize_stage {
+            config.geometry.stage = stage;
+        }
+        config
+    }
+}
+
+/// Фрагмент и прореживание
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Фрагмент")]
+pub struct RangeArgs {
+    /// Начало, секунды или ЧЧ:ММ:СС.мс
+    #[arg(long, value_name = "ВРЕМЯ", value_parser = parse_time_arg)]
+    pub start: Option<f64>,
+
+    /// Конец фрагмента
+    #[arg(long, value_name = "ВРЕМЯ", value_parser = parse_time_arg, conflicts_with = "duration")]
+    pub end: Option<f64>,
+
+    /// Длительность вместо конца
+    #[arg(long, value_name = "ВРЕМЯ", value_parser = parse_time_arg)]
+    pub duration: Option<f64>,
+
+    /// Не больше N кадров
+    #[arg(long, value_name = "N")]
+    pub max_frames: Option<usize>,
+
+    /// Каждый N-й кадр
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub every: usize,
+}
+
+impl RangeArgs {
+    pub fn to_range(&self) -> FrameRange {
+        FrameRange {
+            start: self.start,
+            end: self.end,
+            duration: self.duration,
+            max_frames: self.max_frames,
+            step: self.every,
+        }
+    }
+}
+
+/// Параметры кодирования
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Кодирование")]
+pub struct EncoderArgs {
+    /// Кодек
+    #[arg(long, value_name = "h264|h265|vp9|av1|ffv1|prores|webp")]
+    pub codec: Option<VideoCodec>,
+
+    /// Постоянное качество
+    #[arg(long)]
+    pub crf: Option<u8>,
+
+    /// Квантователь ProRes 1..=31
+    #[arg(long, conflicts_with = "crf")]
+    pub quality: Option<u8>,
+
+    /// Целевой битрейт, например 8M
+    #[arg(long, conflicts_with_all = ["crf", "quality", "lossless"])]
+    pub bitrate: Option<String>,
+
+    /// Сжатие без потерь
+    #[arg(long, conflicts_with_all = ["crf", "quality"])]
+    pub lossless: bool,
+
+    /// Пресет скорости или профиль ProRes
+    #[arg(long)]
+    pub preset: Option<String>,
+
+    /// yuv420p, yuv444p, gray, ...
+    #[arg(long)]
+    pub pix_fmt: Option<EncoderPixelFormat>,
+
+    /// Контейнер (mp4, mkv, mov, webm, avi, nut, ts, webp, y4m)
+    #[arg(long)]
+    pub container: Option<String>,
+}
+
+impl EncoderArgs {
+    /// Параметры поверх `base`; новый кодек заменяет его значения по умолчанию
+    pub fn apply(&self, base: EncoderConfig) -> video_derivative::Result<EncoderConfig> {
+        let mut encoder = match self.codec {
+            Some(codec) => EncoderConfig::new(codec),
+            None => base,
+        };
+        if let Some(crf) = self.crf {
+            encoder = encoder.with_crf(crf);
+        }
+        if let Some(quality) = self.quality {
+            encoder = encoder.with_quality(quality);
+        }
+        if let Some(bitrate) = &self.bitrate {
+            encoder = encoder.with_bitrate(bitrate);
+        }
+        if self.lossless {
+            encoder = encoder.with_lossless();
+        }
+        if let Some(preset) = &self.preset {
+            encoder = encoder.with_preset(preset);
+        }
+        if let Some(pixel_format) = self.pix_fmt {
+            encoder = encoder.with_pixel_format(pixel_format);
+        }
+        if let Some(container) = &self.container {
+            encoder = encoder.with_container(container);
+        }
+        encoder.validate()?;
+        Ok(encoder)
+    }
+}
+
+/// Живой поток: stdin `-`, FIFO или дописываемый файл
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Живой поток")]
+pub struct LiveArgs {
+    /// Обрабатывать кадры по мере поступления
+    #[arg(long)]
+    pub live: bool,
+
+    /// Формат потока (по умолчанию ffmpeg)
+    #[arg(long, value_name = "ffmpeg|y4m|raw", requires = "live")]
+    pub input_format: Option<String>,
+
+    /// Размер сырых RGB24 кадров (нужен и --fps)
+    #[arg(long, value_name = "ШxВ", value_parser = parse_size, requires = "live")]
+    pub raw_size: Option<(u32, u32)>,
+
+    /// Ждать продолжения дописываемого файла
+    #[arg(long, requires = "live")]
+    pub follow: bool,
+
+    /// Конец потока после паузы в секундах (0 - ждать всегда)
+    #[arg(long, value_name = "СЕК", requires = "live")]
+    pub idle_timeout: Option<f64>,
+
+    /// События движения в JSON Lines (по умолчанию stderr)
+    #[arg(long, value_name = "путь|-", requires = "live")]
+    pub events: Option<String>,
+}
+
+/// Пороги поиска событий движения
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "События")]
+pub struct DetectorArgs {
+    /// Доля изменившихся пикселей, с которой начинается событие
+    #[arg(long, default_value_t = 0.01, value_name = "ДОЛЯ")]
+    pub start_fraction: f32,
+
+    /// Доля, ниже которой кадр считается спокойным
+    #[arg(long, default_value_t = 0.005, value_name = "ДОЛЯ")]
+    pub end_fraction: f32,
+
+    /// Столько спокойных кадров подряд завершают событие
+    #[arg(long, default_value_t = 5, value_name = "N")]
+    pub min_quiet_frames: usize,
+}
+
+impl DetectorArgs {
+    pub fn to_detector(&self) -> MotionEventDetector {
+        MotionEventDetector::new(self.start_fraction, self.end_fraction, self.min_quiet_frames)
+    }
+}
+
+/// Разбирает размер вида `640x480`
+pub fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    size.split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or(format!("Некорректный размер: {}", size))
+}
+
+fn parse_time_arg(text: &str) -> Result<f64, String> {
+    parse_time(text).map_err(|e| e.to_string())
+}
+
+/// Файл конфигурации
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Конфигурация")]
+pub struct ConfigArgs {
+    /// Параметры из файла .toml, .json или .yaml; флаги перекрывают их
+    #[arg(long, value_name = "ФАЙЛ")]
+    pub config: Option<PathBuf>,
+
+    /// Вывести итоговую конфигурацию в stdout и выйти
+    #[arg(long, value_name = "toml|json|yaml", num_args = 0..=1, default_missing_value = "toml")]
+    pub print_config: Option<ConfigFormat>,
+}
+
+impl ConfigArgs {
+    /// Файл из `--config` или значения CLI по умолчанию
+    pub fn load(&self) -> video_derivative::Result<ConfigFile> {
+        match &self.config {
+            Some(path) => ConfigFile::load(path),
+            None => {
+                let mut config = ConfigFile::default();
+                config.processing.threshold = DEFAULT_THRESHOLD;
+                Ok(config)
+            }
+        }
+    }
+
+    /// Конфигурация для подкоманд, которые пишут видео
+    pub fn with_output(
+        &self,
+        processing: &ProcessingArgs,
+        encoder: &EncoderArgs,
+        layout: Option<CompositeLayout>,
+        annotate: bool,
+        audio: Option<AudioMode>,
+    ) -> video_derivative::Result<ConfigFile> {
+        let mut config = self.load()?;
+        config.processing = processing.apply(config.processing);
+        config.encoder = encoder.apply(config.encoder)?;
+        if let Some(layout) = layout {
+            config.layout = layout;
+        }
+        if let Some(audio) = audio {
+            config.audio = audio;
+        }
+        config.annotate |= annotate;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Конфигурация только с параметрами обработки (для анализа без кодирования)
+    pub fn with_processing(&self, processing: &ProcessingArgs) -> video_derivative::Result<ConfigFile> {
+        let mut config = self.load()?;
+        config.processing = processing.apply(config.processing);
+        config.validate()?;
+        Ok(config)
+    }
+}
@@ -0,0 +1,520 @@
+//! Подкоманды CLI
+
+use super::args::{AnalyzeArgs, BatchArgs, CompareArgs, ConfigArgs, DeriveArgs, EventsArgs, GenerateArgs, ProbeArgs, Scene};
+use super::{CommandResult, Reporter};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+use video_derivative::processors::{AnalysisSummary, ComparisonSummary, FrameAnalysis, FrameComparison, RegionDetector};
+use video_derivative::video::ffmpeg_wrapper::{is_native_output, is_y4m};
+use video_derivative::video::image_sequence::is_image_sequence_input;
+use video_derivative::video::{
+    collect_inputs, read_manifest, AudioMode, BatchJob, BatchOutcome, BatchRunner, BatchStatus, EncoderConfig,
+    FrameRate, LiveConfig, LiveFormat, LiveInput, MediaInfo, Y4mReader,
+};
+use video_derivative::{ConfigFile, ConfigFormat, FrameGenerator, MotionAnnotator, VideoProcessor, VideoSource};
+
+/// Код выхода `compare`, если видео различаются
+const EXIT_DIFFERENT: u8 = 3;
+
+/// Файл или stdout (`-` или не задан)
+fn open_output(path: Option<&str>) -> Result<Box<dyn Write>, Box<dyn std::error::Error>> {
+    Ok(match path {
+        None | Some("-") => Box::new(BufWriter::new(io::stdout())),
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("Не удалось создать {}: {}", path, e))?,
+        )),
+    })
+}
+
+/// Расширение выходного файла - по контейнеру или кодеку
+fn output_extension(encoder: &EncoderConfig) -> String {
+    match encoder.container.as_deref() {
+        Some("matroska") => "mkv".to_string(),
+        Some(container) => container.to_string(),
+        None => encoder.codec.default_extension().to_string(),
+    }
+}
+
+/// Y4M, изображения, GIF и APNG обрабатываются без FFmpeg
+fn needs_ffmpeg(input: &str, output: &str) -> bool {
+    let native_input = is_image_sequence_input(input) || is_y4m(input);
+    !(native_input && is_native_output(output))
+}
+
+/// `--print-config`: итоговая конфигурация в stdout
+fn print_config(config: &ConfigFile, format: ConfigFormat) -> CommandResult {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{}", config.to_text(format)?)?;
+    if format == ConfigFormat::Json {
+        writeln!(stdout)?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Частота для открытия входа: `--fps`, а для изображений и сырых потоков
+/// еще и `fps` из файла конфигурации. У видео частота берется из самого
+/// файла, иначе значение по умолчанию из конфигурации проредило бы его.
+fn input_fps(cli_fps: Option<u32>, config_args: &ConfigArgs, config: &ConfigFile, needs_fps: bool) -> Option<u32> {
+    cli_fps.or_else(|| (needs_fps && config_args.config.is_some()).then_some(config.processing.fps))
+}
+
+pub fn derive(args: DeriveArgs, reporter: Reporter) -> CommandResult {
+    if args.live.live {
+        return derive_live(args, reporter);
+    }
+
+    let config = args.config()?;
+    if let Some(format) = args.config.print_config {
+        return print_config(&config, format);
+    }
+    let ConfigFile { annotate, layout, processing, encoder, audio } = config.clone();
+    let range = args.range.to_range();
+    range.validate()?;
+    let input = args.input.as_str();
+    let sequence = is_image_sequence_input(input);
+    let fps = input_fps(args.processing.fps, &args.config, &config, sequence);
+
+    if sequence && fps.is_none() {
+        return Err("Для последовательности изображений укажите --fps или fps в --config".into());
+    }
+    if !sequence && !Path::new(input).exists() {
+        return Err(format!("Файл не найден: {}", input).into());
+    }
+
+    let extension = output_extension(&encoder);
+    let output = args.output.unwrap_or_else(|| {
+        let input_path = Path::new(input.trim_end_matches('/'));
+        let stem = if sequence {
+            // Для шаблона берем имя директории, а не `*.png`
+            let directory = if input_path.is_dir() { Some(input_path) } else { input_path.parent() };
+            directory
+                .and_then(|d| d.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "sequence".to_string())
+        } else {
+            input_path
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "video".to_string())
+        };
+        format!("{}_derivative.{}", stem, extension)
+    });
+
+    let mut processor = VideoProcessor::new()?
+        .with_encoder(encoder)
+        .with_layout(layout)
+        .with_range(range)
+        .with_processing(processing.clone())
+        .with_audio(audio)
+        .with_preserved_timestamps(args.preserve_timestamps);
+    if annotate {
+        processor = processor.with_annotations(MotionAnnotator::default());
+    }
+    let processor = reporter.attach(processor);
+
+    if needs_ffmpeg(input, &output) && !processor.is_ffmpeg_available() {
+        return Err("FFmpeg не найден. Установите FFmpeg для обработки видео.".into());
+    }
+
+    let fps = match fps {
+        Some(fps) => FrameRate::from(fps),
+        None => processor.detect_frame_rate(input)?,
+    };
+    reporter.info(format!("Обработка видео: {} -> {}", input, output));
+    let variable_rate = !sequence
+        && !is_y4m(input)
+        && processor.probe(input).ok().and_then(|info| info.video).is_some_and(|video| video.is_variable_frame_rate());
+    if variable_rate && args.preserve_timestamps {
+        reporter.info("Переменная частота кадров: длительности сохраняются, кадры кодируются через временные PNG");
+    } else if variable_rate {
+        reporter.info(format!(
+            "Переменная частота кадров: видео кодируется с постоянной частотой {} (--preserve-timestamps сохранит длительности)",
+            fps
+        ));
+    }
+    reporter.detail(format!("Частота кадров: {}, порог: {}", fps, processing.threshold));
+    reporter.detail(format!("Параметры обработки: {:?}", processing));
+    reporter.detail(format!("Кодирование: {:?}", processor.encoder()));
+
+    processor.create_derivative_video(input, &output, fps, processing.threshold)?;
+    reporter.info(format!("Готово! Результат: {}", output));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Живой режим: stdout может быть занят кадрами, поэтому сообщения идут в stderr
+fn derive_live(args: DeriveArgs, reporter: Reporter) -> CommandResult {
+    let file = args.config()?;
+    if let Some(format) = args.config.print_config {
+        return print_config(&file, format);
+    }
+    let live = &args.live;
+    let fps = input_fps(args.processing.fps, &args.config, &file, true);
+    let format = match (live.input_format.as_deref(), live.raw_size) {
+        (Some("raw") | None, Some((width, height))) => {
+            let fps = fps.ok_or("Для сырого потока укажите --fps")?;
+            LiveFormat::Raw { width, height, fps: fps as f64 }
+        }
+        (Some("raw"), None) => return Err("Для сырого потока укажите --raw-size".into()),
+        (Some(format), _) => format.parse()?,
+        (None, None) if is_y4m(&args.input) => LiveFormat::Y4m,
+        (None, None) => LiveFormat::Ffmpeg,
+    };
+    let mut config = LiveConfig::new(LiveInput::parse(&args.input), format);
+    if live.follow {
+        config = config.with_follow();
+    }
+    if let Some(timeout) = live.idle_timeout {
+        if timeout < 0.0 {
+            return Err(format!("Некорректная пауза: {}", timeout).into());
+        }
+        config = config.with_idle_timeout((timeout > 0.0).then(|| Duration::from_secs_f64(timeout)));
+    }
+
+    // По умолчанию производная уходит в stdout как Y4M
+    let output = args.output.clone().unwrap_or_else(|| "-".to_string());
+    let mut events: Box<dyn Write> = match live.events.as_deref() {
+        None => Box::new(io::stderr()),
+        Some("-") if output == "-" => return Err("stdout уже занят кадрами, укажите файл для --events".into()),
+        Some(path) => open_output(Some(path))?,
+    };
+
+    let ConfigFile { annotate, layout, processing, encoder, audio } = file;
+    if audio != AudioMode::None {
+        return Err("Звук в живом режиме не поддерживается".into());
+    }
+    let range = args.range.to_range();
+    range.validate()?;
+    let mut processor = VideoProcessor::new()?
+        .with_encoder(encoder)
+        .with_layout(layout)
+        .with_range(range)
+        .with_processing(processing.clone());
+    if annotate {
+        processor = processor.with_annotations(MotionAnnotator::default());
+    }
+
+    reporter.info(format!("Живой поток: {} -> {}", args.input, output));
+    let count = processor.create_live_derivative_video(
+        &config,
+        &output,
+        processing.threshold,
+        Default::default(),
+        |event| {
+            writeln!(events, "{}", event.to_json())?;
+            events.flush()?;
+            Ok(())
+        },
+    )?;
+    reporter.info(format!("Поток завершен, обработано кадров: {}", count));
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn analyze(args: AnalyzeArgs, reporter: Reporter) -> CommandResult {
+    let config = args.config.with_processing(&args.processing)?;
+    if let Some(format) = args.config.print_config {
+        return print_config(&config, format);
+    }
+    let fps = input_fps(args.processing.fps, &args.config, &config, is_image_sequence_input(&args.input));
+    let processing = config.processing;
+    let processor = reporter.attach(
+        VideoProcessor::new()?
+            .with_range(args.range.to_range())
+            .with_processing(processing.clone()),
+    );
+    let mut output = open_output(args.output.as_deref())?;
+    let regions = RegionDetector::default();
+    let mut summary = AnalysisSummary::new(args.motion_fraction);
+
+    processor.scan_derivatives(&args.input, fps, processing.threshold, |derivative| {
+        let analysis = FrameAnalysis::new(derivative, &regions);
+        summary.add(&analysis);
+        if !args.summary_only {
+            writeln!(output, "{}", analysis.to_json())?;
+        }
+        Ok(())
+    })?;
+
+    if args.summary_only {
+        writeln!(output, "{}", serde_json::to_string(&summary)?)?;
+    }
+    output.flush()?;
+    reporter.info(format!(
+        "Кадров: {}, с движением: {}, средняя доля изменений: {:.4}, пик: {:.4}{}",
+        summary.frames,
+        summary.motion_frames,
+        summary.mean_changed_fraction,
+        summary.peak_changed_fraction,
+        summary.peak_frame.map(|frame| format!(" (кадр {})", frame)).unwrap_or_default()
+    ));
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn events(args: EventsArgs, reporter: Reporter) -> CommandResult {
+    let config = args.config.with_processing(&args.processing)?;
+    if let Some(format) = args.config.print_config {
+        return print_config(&config, format);
+    }
+    let fps = input_fps(args.processing.fps, &args.config, &config, is_image_sequence_input(&args.input));
+    let processing = config.processing;
+    let processor = reporter.attach(
+        VideoProcessor::new()?
+            .with_range(args.range.to_range())
+            .with_processing(processing.clone()),
+    );
+    let mut output = open_output(args.output.as_deref())?;
+    let mut detector = args.detector.to_detector();
+    let mut found = 0;
+
+    processor.scan_derivatives(&args.input, fps, processing.threshold, |derivative| {
+        if let Some(event) = detector.update(derivative) {
+            writeln!(output, "{}", event.to_json())?;
+            found += 1;
+        }
+        Ok(())
+    })?;
+    if let Some(event) = detector.finish() {
+        writeln!(output, "{}", event.to_json())?;
+        found += 1;
+    }
+    output.flush()?;
+    reporter.info(format!("Найдено событий: {}", found));
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn probe(args: ProbeArgs, reporter: Reporter) -> CommandResult {
+    let mut stdout = io::stdout().lock();
+
+    // Y4M описывается заголовком, ffprobe не нужен
+    if is_y4m(&args.input) {
+        let reader = Y4mReader::open(&args.input)?;
+        let header = reader.header();
+        if args.json {
+            let info = serde_json::json!({
+                "format_name": "yuv4mpegpipe",
+                "width": header.width,
+                "height": header.height,
+                "frame_rate": header.fps(),
+            });
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&info)?)?;
+        } else {
+            writeln!(stdout, "Формат: yuv4mpegpipe")?;
+            writeln!(stdout, "Видео: {}x{}, {:.3} к/с", header.width, header.height, header.fps())?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let processor = VideoProcessor::new()?;
+    reporter.detail(format!("FFmpeg доступен: {}", processor.is_ffmpeg_available()));
+    let info = processor.probe(&args.input)?;
+    if args.json {
+        writeln!(stdout, "{}", serde_json::to_string_pretty(&info)?)?;
+    } else {
+        print_media_info(&mut stdout, &info)?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_media_info(out: &mut impl Write, info: &MediaInfo) -> io::Result<()> {
+    writeln!(out, "Формат: {}", info.format_name)?;
+    if let Some(duration) = info.duration {
+        writeln!(out, "Длительность: {:.3} с", duration)?;
+    }
+    if let Some(bit_rate) = info.bit_rate {
+        writeln!(out, "Битрейт: {} бит/с", bit_rate)?;
+    }
+    match &info.video {
+        Some(video) => {
+            let (width, height) = video.display_dimensions();
+            write!(out, "Видео #{}: {} {}x{}", video.index, video.codec, width, height)?;
+            if let Some(rate) = video.effective_frame_rate() {
+                write!(out, ", {:.3} к/с", rate)?;
+            }
+            if video.is_variable_frame_rate() {
+                write!(out, " (переменная)")?;
+            }
+            if let Some(pixel_format) = &video.pixel_format {
+                write!(out, ", {}", pixel_format)?;
+            }
+            if let Some(frames) = video.frame_count {
+                write!(out, ", {} кадров", frames)?;
+            }
+            writeln!(out)?;
+        }
+        None => writeln!(out, "Видео: нет")?,
+    }
+    for audio in &info.audio_streams {
+        write!(out, "Звук #{}: {}", audio.index, audio.codec)?;
+        if let Some(rate) = audio.sample_rate {
+            write!(out, ", {} Гц", rate)?;
+        }
+        if let Some(layout) = &audio.channel_layout {
+            write!(out, ", {}", layout)?;
+        } else if let Some(channels) = audio.channels {
+            write!(out, ", {} кан.", channels)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+pub fn generate(args: GenerateArgs, reporter: Reporter) -> CommandResult {
+    // Объекты сцены шириной до 80 пикселей должны помещаться в кадр
+    if args.width <= 80 || args.height == 0 {
+        return Err(format!("Слишком маленький кадр: {}x{} (нужна ширина больше 80)", args.width, args.height).into());
+    }
+    if args.fps == 0 || args.frames == 0 {
+        return Err("Частота и количество кадров должны быть больше нуля".into());
+    }
+
+    let generator = FrameGenerator::new(args.width, args.height, args.fps);
+    let frames = match args.scene {
+        Scene::Moving => generator.generate_moving_object_frames(args.frames),
+        Scene::Complex => generator.generate_complex_scene_frames(args.frames),
+    };
+    let processor = reporter.attach(VideoProcessor::new()?.with_encoder(args.encoder.apply(Default::default())?));
+    processor.save_frames_to_video(&frames, &args.output, args.fps)?;
+    reporter.info(format!("Создано {} кадров: {}", frames.len(), args.output));
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn compare(args: CompareArgs, reporter: Reporter) -> CommandResult {
+    let processor = VideoProcessor::new()?.with_range(args.range.to_range());
+    let mut first = processor.open_source(&args.first, args.fps)?;
+    let mut second = processor.open_source(&args.second, args.fps)?;
+    let mut output = args.output.as_deref().map(|path| open_output(Some(path))).transpose()?;
+    let mut summary = ComparisonSummary::default();
+
+    let length_mismatch = loop {
+        match (first.next_frame()?, second.next_frame()?) {
+            (Some(a), Some(b)) => {
+                let comparison = FrameComparison::between(&a, &b, args.tolerance)?;
+                summary.add(&comparison);
+                if let Some(output) = output.as_mut() {
+                    writeln!(output, "{}", comparison.to_json())?;
+                }
+            }
+            (None, None) => break false,
+            _ => break true,
+        }
+    };
+    if let Some(output) = output.as_mut() {
+        output.flush()?;
+    }
+
+    let psnr = summary.min_psnr.map_or("бесконечность".to_string(), |psnr| format!("{:.2} дБ", psnr));
+    reporter.info(format!(
+        "Кадров: {}, различаются: {}, средняя MSE: {:.3}, наименьший PSNR: {}, наибольшая разница: {}",
+        summary.frames, summary.differing_frames, summary.mean_mse, psnr, summary.max_difference
+    ));
+
+    let within = match args.min_psnr {
+        Some(min_psnr) => summary.min_psnr.is_none_or(|psnr| psnr >= min_psnr),
+        None => summary.is_identical(),
+    };
+    if length_mismatch {
+        reporter.info("Видео различаются по количеству кадров");
+    }
+    if length_mismatch || !within {
+        reporter.info("Видео различаются");
+        return Ok(ExitCode::from(EXIT_DIFFERENT));
+    }
+    reporter.info("Видео совпадают");
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn batch(args: BatchArgs, reporter: Reporter) -> CommandResult {
+    let config = args.config()?;
+    if let Some(format) = args.config.print_config {
+        return print_config(&config, format);
+    }
+    let range = args.range.to_range();
+    range.validate()?;
+
+    let mut inputs = Vec::new();
+    for spec in &args.inputs {
+        inputs.extend(collect_inputs(spec)?);
+    }
+    if let Some(manifest) = &args.manifest {
+        inputs.extend(read_manifest(manifest)?);
+    }
+    let jobs = BatchJob::plan(&inputs, &args.output_dir, &output_extension(&config.encoder))?;
+    if jobs.is_empty() {
+        return Err("Не найдено ни одного видео".into());
+    }
+
+    // Проверяем FFmpeg один раз, а не в каждой задаче
+    let ffmpeg_needed = jobs
+        .iter()
+        .any(|job| needs_ffmpeg(&job.input.to_string_lossy(), &job.output.to_string_lossy()));
+    if ffmpeg_needed && !VideoProcessor::new()?.is_ffmpeg_available() {
+        return Err("FFmpeg не найден. Установите FFmpeg для обработки видео.".into());
+    }
+
+    let mut runner = args.jobs.map_or_else(BatchRunner::default, BatchRunner::new);
+    if args.force {
+        runner = runner.with_overwrite();
+    }
+    reporter.info(format!("Видео: {}, одновременно: {}", jobs.len(), runner.jobs()));
+    reporter.detail(format!("Параметры обработки: {:?}", config.processing));
+    reporter.detail(format!("Кодирование: {:?}", config.encoder));
+
+    let total = jobs.len();
+    let report = runner.run(
+        &jobs,
+        |input, output| {
+            let (input, output) = (input.to_string_lossy(), output.to_string_lossy());
+            let mut processor = VideoProcessor::new()?
+                .with_encoder(config.encoder.clone())
+                .with_layout(config.layout)
+                .with_range(range.clone())
+                .with_processing(config.processing.clone())
+                .with_audio(config.audio.clone());
+            if config.annotate {
+                processor = processor.with_annotations(MotionAnnotator::default());
+            }
+            let fps = match args.processing.fps {
+                Some(fps) => FrameRate::from(fps),
+                None => processor.detect_frame_rate(&input)?,
+            };
+            processor.create_derivative_video(&input, &output, fps, config.processing.threshold)
+        },
+        |finished, outcome| reporter.info(format!("[{}/{}] {}", finished, total, describe_outcome(outcome))),
+    )?;
+
+    if let Some(path) = &args.report {
+        let mut output = open_output(Some(path))?;
+        writeln!(output, "{}", report.to_json())?;
+        output.flush()?;
+    }
+    reporter.info(format!(
+        "Готово: {}, пропущено: {}, ошибок: {} за {:.1} с",
+        report.done, report.skipped, report.failed, report.seconds
+    ));
+    if !report.is_success() {
+        for item in report.items.iter().filter(|item| item.status == BatchStatus::Failed) {
+            reporter.info(format!("  {}: {}", item.input.display(), item.error.as_deref().unwrap_or_default()));
+        }
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn describe_outcome(outcome: &BatchOutcome) -> String {
+    match outcome.status {
+        BatchStatus::Done => format!(
+            "готово {} -> {} ({:.1} с)",
+            outcome.input.display(),
+            outcome.output.display(),
+            outcome.seconds
+        ),
+        BatchStatus::Skipped => format!("пропущено {}: результат уже есть", outcome.input.display()),
+        BatchStatus::Failed => format!(
+            "ошибка {}: {}",
+            outcome.input.display(),
+            outcome.error.as_deref().unwrap_or_default()
+        ),
+    }
+}
@@ -0,0 +1,83 @@
+//! Командная строка: разбор параметров и подкоманды
+
+pub mod args;
+pub mod commands;
+
+use args::{Cli, Command, Verbosity};
+use clap::Parser;
+use std::ffi::OsString;
+use std::process::ExitCode;
+use video_derivative::video::TerminalProgress;
+use video_derivative::VideoProcessor;
+
+/// Результат подкоманды, отличный от ошибки (например, «видео различаются»)
+pub type CommandResult = Result<ExitCode, Box<dyn std::error::Error>>;
+
+/// Сообщения пользователю в stderr с учетом `--quiet`/`--verbose`
+#[derive(Debug, Clone, Copy)]
+pub struct Reporter {
+    verbosity: Verbosity,
+}
+
+impl Reporter {
+    pub fn new(verbosity: Verbosity) -> Self {
+        Self { verbosity }
+    }
+
+    /// Обычное сообщение; подавляется `--quiet`
+    pub fn info(&self, message: impl AsRef<str>) {
+        if !self.verbosity.quiet {
+            eprintln!("{}", message.as_ref());
+        }
+    }
+
+    /// Подробности; только с `--verbose`
+    pub fn detail(&self, message: impl AsRef<str>) {
+        if self.verbosity.verbose > 0 {
+            eprintln!("{}", message.as_ref());
+        }
+    }
+
+    /// Полоса прогресса; при `--quiet` обработка идет молча
+    pub fn attach(&self, processor: VideoProcessor) -> VideoProcessor {
+        if self.verbosity.quiet {
+            processor
+        } else {
+            processor.with_progress(TerminalProgress::new())
+        }
+    }
+}
+
+/// Старый вызов `cli <видео> [параметры]` означает `cli derive <видео> [параметры]`
+fn with_default_command(mut args: Vec<OsString>) -> Vec<OsString> {
+    let needs_command = args.get(1).and_then(|arg| arg.to_str()).is_some_and(|arg| {
+        // `-` - stdin в живом режиме, а не флаг
+        (arg == "-" || !arg.starts_with('-')) && !Command::NAMES.contains(&arg)
+    });
+    if needs_command {
+        args.insert(1, "derive".into());
+    }
+    args
+}
+
+pub fn run(args: impl IntoIterator<Item = OsString>) -> ExitCode {
+    let cli = Cli::parse_from(with_default_command(args.into_iter().collect()));
+    let reporter = Reporter::new(cli.verbosity);
+
+    let result = match cli.command {
+        Command::Derive(args) => commands::derive(args, reporter),
+        Command::Analyze(args) => commands::analyze(args, reporter),
+        Command::Events(args) => commands::events(args, reporter),
+        Command::Probe(args) => commands::probe(args, reporter),
+        Command::Generate(args) => commands::generate(args, reporter),
+        Command::Compare(args) => commands::compare(args, reporter),
+        Command::Batch(args) => commands::batch(args, reporter),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
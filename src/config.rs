@@ -0,0 +1,127 @@
+//! Файл конфигурации: параметры обработки, кодирования и раскладки в
+//! TOML, JSON или YAML. Отсутствующие поля берутся по умолчанию, поэтому
+//! в файле достаточно указать только то, что отличается (например, порог
+//! и область обрезки для конкретной камеры).
+
+use crate::error::{Error, Result};
+use crate::processors::CompositeLayout;
+use crate::types::ProcessingConfig;
+use crate::video::{AudioMode, EncoderConfig};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Формат файла конфигурации
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Формат по расширению файла
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        extension.parse().map_err(|_| {
+            Error::config(format!(
+                "{}: неизвестный формат конфигурации (ожидается .toml, .json, .yaml или .yml)",
+                path.display()
+            ))
+        })
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "toml" => Ok(ConfigFormat::Toml),
+            "json" => Ok(ConfigFormat::Json),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            other => Err(Error::config(format!(
+                "Неизвестный формат конфигурации: {} (доступны toml, json, yaml)",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+            ConfigFormat::Yaml => "yaml",
+        })
+    }
+}
+
+/// Полная конфигурация обработки, которую можно хранить в репозитории
+///
+/// ```toml
+/// layout = "side-by-side"
+/// audio = "copy"
+///
+/// [processing]
+/// threshold = 20
+/// geometry = { crop = { x = 0, y = 120, width = 1280, height = 600 } }
+///
+/// [encoder]
+/// codec = "h265"
+/// rate_control = { crf = 26 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Рамки, номера треков и траектории движения
+    pub annotate: bool,
+    pub layout: CompositeLayout,
+    pub processing: ProcessingConfig,
+    pub encoder: EncoderConfig,
+    /// Звук исходного видео в результате
+    pub audio: AudioMode,
+}
+
+impl ConfigFile {
+    /// Читает и проверяет файл; формат определяется по расширению
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let text = fs::read_to_string(path).map_err(|e| Error::file(path, e))?;
+        Self::parse(&text, format).map_err(|e| match e {
+            Error::InvalidConfig(message) => Error::config(format!("{}: {}", path.display(), message)),
+            other => other,
+        })
+    }
+
+    /// Разбирает и проверяет текст конфигурации
+    pub fn parse(text: &str, format: ConfigFormat) -> Result<Self> {
+        let config: Self = match format {
+            ConfigFormat::Toml => toml::from_str(text).map_err(|e| Error::config(e.to_string().trim_end().to_string()))?,
+            ConfigFormat::Json => serde_json::from_str(text).map_err(|e| Error::config(e.to_string()))?,
+            ConfigFormat::Yaml => serde_yaml::from_str(text).map_err(|e| Error::config(e.to_string()))?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Текст конфигурации, который `parse` прочитает обратно
+    pub fn to_text(&self, format: ConfigFormat) -> Result<String> {
+        match format {
+            ConfigFormat::Toml => toml::to_string_pretty(self).map_err(Error::encode),
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            ConfigFormat::Yaml => serde_yaml::to_string(self).map_err(Error::encode),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.processing.validate()?;
+        self.encoder.validate()
+    }
+}
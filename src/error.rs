@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Ошибки библиотеки
+#[derive(Debug, Error)]
+pub enum Error {
+    /// FFmpeg (или ffprobe) не установлен или не найден в PATH
+    #[error("FFmpeg не найден. Установите FFmpeg для обработки видео")]
+    FfmpegNotFound,
+
+    /// Процесс FFmpeg/ffprobe завершился с ошибкой
+    #[error("FFmpeg завершился с ошибкой{}: {stderr}", exit_code_suffix(*code))]
+    FfmpegFailed { code: Option<i32>, stderr: String },
+
+    /// Поврежденные или неподдерживаемые входные данные
+    #[error("Ошибка декодирования: {0}")]
+    Decode(String),
+
+    /// Не удалось записать результат
+    #[error("Ошибка кодирования: {0}")]
+    Encode(String),
+
+    #[error("Ошибка ввода-вывода: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Ошибка ввода-вывода для конкретного файла
+    #[error("{}: {source}", path.display())]
+    File {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Ошибка изображения: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error("Некорректный JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// Некорректные параметры или их сочетание
+    #[error("Некорректные параметры: {0}")]
+    InvalidConfig(String),
+
+    /// Кадр не совпадает по размеру с потоком или заголовком
+    #[error("Размер кадра {}x{} отличается от ожидаемого {}x{}", actual.0, actual.1, expected.0, expected.1)]
+    DimensionMismatch { expected: (u32, u32), actual: (u32, u32) },
+
+    /// Производную не к чему применить: перед ней не было опорного кадра
+    #[error("Нет опорного кадра для восстановления")]
+    MissingReference,
+
+    /// Операция прервана через `CancellationToken`
+    #[error("Операция отменена")]
+    Cancelled,
+
+    /// Источник не дал ни одного кадра
+    #[error("Не удалось извлечь ни одного кадра: {0}")]
+    NoFrames(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn file(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Error::File { path: path.into(), source }
+    }
+
+    pub(crate) fn decode(message: impl Into<String>) -> Self {
+        Error::Decode(message.into())
+    }
+
+    pub(crate) fn encode(message: impl ToString) -> Self {
+        Error::Encode(message.to_string())
+    }
+
+    pub(crate) fn config(message: impl Into<String>) -> Self {
+        Error::InvalidConfig(message.into())
+    }
+
+    /// Ошибка запуска внешней программы: отсутствие бинарника - `FfmpegNotFound`
+    pub(crate) fn spawn(source: std::io::Error) -> Self {
+        if source.kind() == std::io::ErrorKind::NotFound {
+            Error::FfmpegNotFound
+        } else {
+            Error::Io(source)
+        }
+    }
+
+    /// Ошибка завершившегося процесса FFmpeg
+    pub(crate) fn ffmpeg(status: std::process::ExitStatus, stderr: impl Into<String>) -> Self {
+        Error::FfmpegFailed { code: status.code(), stderr: stderr.into() }
+    }
+}
+
+fn exit_code_suffix(code: Option<i32>) -> String {
+    code.map(|code| format!(" (код {})", code)).unwrap_or_default()
+}
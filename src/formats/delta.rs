@@ -0,0 +1,472 @@
+//! Компактный контейнер знаковых производных (`.vdf`).
+//!
+//! Структура файла:
+//! - заголовок: магия `VDF1`, размеры, fps, формат пикселей, период опорных кадров;
+//! - записи кадров: тип (опорный/разница), номер, метка времени, длина и полезная нагрузка;
+//! - индекс всех записей для перемотки и футер со смещением индекса.
+//!
+//! Опорные кадры хранятся как сырые байты, разницы - как последовательность
+//! серий `(пропуск нулей, длина серии, значения)` в zigzag-varint кодировании,
+//! поэтому почти статичные сцены занимают очень мало места.
+
+use crate::error::{Error, Result};
+use crate::processors::DerivativeIntegrator;
+use crate::types::{DerivativePacket, FrameBuffer, FramePixel, PixelFormat, Sample, SignedDerivative, VideoFrame};
+use image::Rgb;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"VDF1";
+const INDEX_MAGIC: &[u8; 4] = b"VDIX";
+const FORMAT_VERSION: u16 = 1;
+
+const RECORD_KEYFRAME: u8 = 0;
+const RECORD_DELTA: u8 = 1;
+
+/// Размер записи индекса: номер, метка времени, смещение и флаг опорного кадра
+const INDEX_ENTRY_LEN: u64 = 25;
+/// Размер футера: смещение индекса и магия индекса
+const FOOTER_LEN: u64 = 12;
+/// Заголовок записи кадра: тип, номер, метка времени и длина нагрузки
+const RECORD_HEADER_LEN: u64 = 21;
+/// Предел площади кадра (16384x16384): больший размер в заголовке считаем
+/// поврежденным, а не выделяем под него буфер разницы
+const MAX_FRAME_PIXELS: u64 = 1 << 28;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaHeader {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub pixel_format: PixelFormat,
+    pub keyframe_interval: u32,
+}
+
+impl DeltaHeader {
+    pub fn new(width: u32, height: u32, fps: u32) -> Self {
+        Self {
+            width,
+            height,
+            fps,
+            pixel_format: PixelFormat::Rgb8,
+            keyframe_interval: 30,
+        }
+    }
+
+    /// Количество компонент в одном кадре
+    fn frame_len(&self) -> usize {
+        self.width as usize * self.height as usize * self.pixel_format.channels()
+    }
+}
+
+/// Запись индекса: положение кадра в файле
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexEntry {
+    pub frame_number: u64,
+    pub timestamp: f64,
+    pub offset: u64,
+    pub is_keyframe: bool,
+}
+
+pub struct DeltaFileWriter<W: Write, P: FramePixel = Rgb<u8>> {
+    writer: W,
+    header: DeltaHeader,
+    index: Vec<IndexEntry>,
+    position: u64,
+    _pixel: PhantomData<P>,
+}
+
+impl<P: FramePixel> DeltaFileWriter<BufWriter<File>, P> {
+    pub fn create<Q: AsRef<Path>>(path: Q, header: DeltaHeader) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), header)
+    }
+}
+
+impl<W: Write, P: FramePixel> DeltaFileWriter<W, P> {
+    /// Создает писатель; формат пикселей в заголовке берется из типа `P`
+    pub fn new(writer: W, header: DeltaHeader) -> Result<Self> {
+        let mut this = Self {
+            writer,
+            header: DeltaHeader { pixel_format: P::FORMAT, ..header },
+            index: Vec::new(),
+            position: 0,
+            _pixel: PhantomData,
+        };
+
+        let mut buf = Vec::with_capacity(23);
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        buf.extend_from_slice(&this.header.width.to_le_bytes());
+        buf.extend_from_slice(&this.header.height.to_le_bytes());
+        buf.extend_from_slice(&this.header.fps.to_le_bytes());
+        buf.push(pixel_format_to_byte(this.header.pixel_format));
+        buf.extend_from_slice(&this.header.keyframe_interval.to_le_bytes());
+        this.write_bytes(&buf)?;
+
+        Ok(this)
+    }
+
+    pub fn header(&self) -> &DeltaHeader {
+        &self.header
+    }
+
+    /// Записывает опорный кадр или разницу
+    pub fn write_packet(&mut self, packet: &DerivativePacket<P>) -> Result<()> {
+        let (record_type, payload) = match packet {
+            DerivativePacket::Keyframe(frame) => {
+                if frame.dimensions() != (self.header.width, self.header.height) {
+                    return Err(Error::DimensionMismatch {
+                        expected: (self.header.width, self.header.height),
+                        actual: frame.dimensions(),
+                    });
+                }
+                let mut payload = Vec::with_capacity(self.header.frame_len() * <P::Subpixel as Sample>::BYTES);
+                for &value in frame.data.as_raw() {
+                    value.write_le(&mut payload);
+                }
+                (RECORD_KEYFRAME, payload)
+            }
+            DerivativePacket::Delta(derivative) => {
+                if derivative.data.len() != self.header.frame_len() {
+                    return Err(Error::DimensionMismatch {
+                        expected: (self.header.width, self.header.height),
+                        actual: derivative.dimensions(),
+                    });
+                }
+                (RECORD_DELTA, encode_sparse::<P::Subpixel>(&derivative.data))
+            }
+        };
+
+        self.index.push(IndexEntry {
+            frame_number: packet.frame_number() as u64,
+            timestamp: packet.timestamp(),
+            offset: self.position,
+            is_keyframe: record_type == RECORD_KEYFRAME,
+        });
+
+        let mut record = Vec::with_capacity(payload.len() + 21);
+        record.push(record_type);
+        record.extend_from_slice(&(packet.frame_number() as u64).to_le_bytes());
+        record.extend_from_slice(&packet.timestamp().to_le_bytes());
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(&payload);
+        self.write_bytes(&record)
+    }
+
+    /// Записывает индекс и футер, возвращает исходный писатель
+    pub fn finish(mut self) -> Result<W> {
+        let index_offset = self.position;
+
+        let mut buf = Vec::with_capacity(8 + self.index.len() * 25 + 12);
+        buf.extend_from_slice(&(self.index.len() as u64).to_le_bytes());
+        for entry in &self.index {
+            buf.extend_from_slice(&entry.frame_number.to_le_bytes());
+            buf.extend_from_slice(&entry.timestamp.to_le_bytes());
+            buf.extend_from_slice(&entry.offset.to_le_bytes());
+            buf.push(entry.is_keyframe as u8);
+        }
+        buf.extend_from_slice(&index_offset.to_le_bytes());
+        buf.extend_from_slice(INDEX_MAGIC);
+        self.write_bytes(&buf)?;
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+pub struct DeltaFileReader<R: Read + Seek, P: FramePixel = Rgb<u8>> {
+    reader: R,
+    header: DeltaHeader,
+    index: Vec<IndexEntry>,
+    /// Конец записей кадров (начало индекса)
+    data_end: u64,
+    _pixel: PhantomData<P>,
+}
+
+impl<P: FramePixel> DeltaFileReader<BufReader<File>, P> {
+    pub fn open<Q: AsRef<Path>>(path: Q) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek, P: FramePixel> DeltaFileReader<R, P> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::decode("Файл не является контейнером производных"));
+        }
+        let version = read_u16(&mut reader)?;
+        if version != FORMAT_VERSION {
+            return Err(Error::decode(format!("Неподдерживаемая версия контейнера: {}", version)));
+        }
+        let width = read_u32(&mut reader)?;
+        let height = read_u32(&mut reader)?;
+        let fps = read_u32(&mut reader)?;
+        let pixel_format = pixel_format_from_byte(read_u8(&mut reader)?)?;
+        if pixel_format != P::FORMAT {
+            return Err(Error::decode(format!(
+                "Формат пикселей контейнера {:?} не совпадает с запрошенным {:?}",
+                pixel_format,
+                P::FORMAT
+            )));
+        }
+        let keyframe_interval = read_u32(&mut reader)?;
+        if width == 0 || height == 0 || width as u64 * height as u64 > MAX_FRAME_PIXELS {
+            return Err(Error::decode(format!("Недопустимый размер кадра контейнера: {}x{}", width, height)));
+        }
+        let header = DeltaHeader { width, height, fps, pixel_format, keyframe_interval };
+
+        // Футер: смещение индекса и магия индекса
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        let corrupted = || Error::decode("Индекс контейнера поврежден или отсутствует");
+        reader.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+        let index_offset = read_u64(&mut reader)?;
+        reader.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            return Err(corrupted());
+        }
+
+        // Число записей не может превышать место, которое индекс занимает в файле
+        let index_len = file_len
+            .checked_sub(FOOTER_LEN + 8)
+            .and_then(|end| end.checked_sub(index_offset))
+            .ok_or_else(corrupted)?;
+        reader.seek(SeekFrom::Start(index_offset))?;
+        let count = read_u64(&mut reader)?;
+        if count > index_len / INDEX_ENTRY_LEN {
+            return Err(corrupted());
+        }
+        let count = count as usize;
+        let mut index = Vec::with_capacity(count);
+        for _ in 0..count {
+            index.push(IndexEntry {
+                frame_number: read_u64(&mut reader)?,
+                timestamp: read_f64(&mut reader)?,
+                offset: read_u64(&mut reader)?,
+                is_keyframe: read_u8(&mut reader)? != 0,
+            });
+        }
+
+        Ok(Self { reader, header, index, data_end: index_offset, _pixel: PhantomData })
+    }
+
+    pub fn header(&self) -> &DeltaHeader {
+        &self.header
+    }
+
+    pub fn index(&self) -> &[IndexEntry] {
+        &self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Читает запись по ее позиции в индексе
+    pub fn read_packet(&mut self, position: usize) -> Result<DerivativePacket<P>> {
+        let entry = *self
+            .index
+            .get(position)
+            .ok_or_else(|| Error::config(format!("Кадр {} отсутствует в контейнере", position)))?;
+
+        self.reader.seek(SeekFrom::Start(entry.offset))?;
+        let record_type = read_u8(&mut self.reader)?;
+        let frame_number = read_u64(&mut self.reader)? as usize;
+        let timestamp = read_f64(&mut self.reader)?;
+        let len = read_u32(&mut self.reader)? as usize;
+        // Длину проверяем до выделения памяти: опорный кадр имеет ровно размер
+        // кадра, а разница не может выходить за область записей
+        let keyframe_len = self.header.frame_len() * <P::Subpixel as Sample>::BYTES;
+        let available = self.data_end.saturating_sub(entry.offset.saturating_add(RECORD_HEADER_LEN));
+        if (record_type == RECORD_KEYFRAME && len != keyframe_len) || len as u64 > available {
+            return Err(Error::decode(format!("Некорректная длина записи кадра {}: {} байт", position, len)));
+        }
+        let mut payload = vec![0u8; len];
+        self.reader.read_exact(&mut payload)?;
+
+        let (width, height) = (self.header.width, self.header.height);
+        match record_type {
+            RECORD_KEYFRAME => {
+                let samples = payload
+                    .chunks_exact(<P::Subpixel as Sample>::BYTES)
+                    .map(<P::Subpixel as Sample>::read_le)
+                    .collect();
+                let data = FrameBuffer::<P>::from_raw(width, height, samples)
+                    .ok_or_else(|| Error::decode("Размер опорного кадра не совпадает с заголовком"))?;
+                Ok(DerivativePacket::Keyframe(VideoFrame::from_buffer(data, frame_number, timestamp)))
+            }
+            RECORD_DELTA => {
+                let data = decode_sparse::<P::Subpixel>(&payload, self.header.frame_len())?;
+                Ok(DerivativePacket::Delta(SignedDerivative::new(data, width, height, frame_number, timestamp)))
+            }
+            other => Err(Error::decode(format!("Неизвестный тип записи: {}", other))),
+        }
+    }
+
+    /// Восстанавливает кадр по позиции, начиная с ближайшего предшествующего опорного кадра
+    pub fn frame_at(&mut self, position: usize) -> Result<VideoFrame<P>> {
+        if position >= self.index.len() {
+            return Err(Error::config(format!("Кадр {} отсутствует в контейнере", position)));
+        }
+        let keyframe = self.index[..=position]
+            .iter()
+            .rposition(|entry| entry.is_keyframe)
+            .ok_or(Error::MissingReference)?;
+
+        let mut integrator = DerivativeIntegrator::new();
+        let mut frame = integrator.push(&self.read_packet(keyframe)?)?;
+        for i in keyframe + 1..=position {
+            frame = integrator.push(&self.read_packet(i)?)?;
+        }
+        Ok(frame)
+    }
+
+    /// Последовательно читает все записи контейнера
+    pub fn packets(&mut self) -> impl Iterator<Item = Result<DerivativePacket<P>>> + '_ {
+        (0..self.index.len()).map(move |i| self.read_packet(i))
+    }
+}
+
+/// Кодирует разницу сериями: varint(пропуск нулей), varint(длина), закодированные значения
+fn encode_sparse<S: Sample>(data: &[S::Delta]) -> Vec<u8> {
+    let zero = S::Delta::default();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let start = i;
+        while i < data.len() && data[i] == zero {
+            i += 1;
+        }
+        if i == data.len() {
+            break;
+        }
+        let skip = i - start;
+        let run_start = i;
+        while i < data.len() && data[i] != zero {
+            i += 1;
+        }
+        write_varint(&mut out, skip as u64);
+        write_varint(&mut out, (i - run_start) as u64);
+        for &value in &data[run_start..i] {
+            write_varint(&mut out, S::encode_delta(value));
+        }
+    }
+    out
+}
+
+fn decode_sparse<S: Sample>(payload: &[u8], len: usize) -> Result<Vec<S::Delta>> {
+    let mut data = vec![S::Delta::default(); len];
+    let mut cursor = 0;
+    let mut position = 0usize;
+    let out_of_bounds = || Error::decode("Серия выходит за границы кадра");
+    while cursor < payload.len() {
+        let skip = read_varint(payload, &mut cursor)?;
+        let run = read_varint(payload, &mut cursor)?;
+        let start = usize::try_from(skip).ok().and_then(|skip| position.checked_add(skip)).ok_or_else(out_of_bounds)?;
+        let end = usize::try_from(run).ok().and_then(|run| start.checked_add(run)).ok_or_else(out_of_bounds)?;
+        if end > len {
+            return Err(out_of_bounds());
+        }
+        for value in &mut data[start..end] {
+            *value = S::decode_delta(read_varint(payload, &mut cursor)?);
+        }
+        position = end;
+    }
+    Ok(data)
+}
+
+fn pixel_format_to_byte(format: PixelFormat) -> u8 {
+    match format {
+        PixelFormat::Rgb8 => 0,
+        PixelFormat::Rgba8 => 1,
+        PixelFormat::Luma8 => 2,
+        PixelFormat::Rgb16 => 3,
+        PixelFormat::Rgba16 => 4,
+        PixelFormat::Luma16 => 5,
+        PixelFormat::Rgb32F => 6,
+        PixelFormat::Luma32F => 7,
+    }
+}
+
+fn pixel_format_from_byte(value: u8) -> Result<PixelFormat> {
+    match value {
+        0 => Ok(PixelFormat::Rgb8),
+        1 => Ok(PixelFormat::Rgba8),
+        2 => Ok(PixelFormat::Luma8),
+        3 => Ok(PixelFormat::Rgb16),
+        4 => Ok(PixelFormat::Rgba16),
+        5 => Ok(PixelFormat::Luma16),
+        6 => Ok(PixelFormat::Rgb32F),
+        7 => Ok(PixelFormat::Luma32F),
+        other => Err(Error::decode(format!("Неизвестный формат пикселей: {}", other))),
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(payload: &[u8], cursor: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *payload.get(*cursor).ok_or_else(|| Error::decode("Неожиданный конец данных производной"))?;
+        *cursor += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+        if shift >= 64 {
+            return Err(Error::decode("Слишком длинное varint-число"));
+        }
+    }
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> Result<f64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
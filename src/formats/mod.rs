@@ -0,0 +1,2 @@
+pub mod delta;
+pub use delta::{DeltaFileReader, DeltaFileWriter, DeltaHeader};
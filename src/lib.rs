@@ -2,18 +2,26 @@ pub mod types;
 pub mod processors;
 pub mod video;
 pub mod utils;
+pub mod formats;
+pub mod render;
+pub mod error;
+pub mod config;
 
 // Re-export основных компонентов для удобства использования
-pub use types::{VideoFrame, ProcessingConfig};
-pub use processors::VideoDerivativeProcessor;
-pub use video::VideoProcessor;
+pub use types::{VideoFrame, ProcessingConfig, DifferenceBackend, TimeNormalization, SignedDerivative, DerivativePacket, FramePixel, PixelFormat, GeometryConfig, ResizeMode};
+pub use processors::{VideoDerivativeProcessor, DerivativeIntegrator, CompositeLayout};
+pub use video::{VideoProcessor, VideoSource, VideoSink};
 pub use utils::FrameGenerator;
+pub use formats::{DeltaFileReader, DeltaFileWriter, DeltaHeader};
+pub use render::{Annotator, MotionAnnotator};
+pub use error::{Error, Result};
+pub use config::{ConfigFile, ConfigFormat};
 
 #[cfg(test)]
 mod tests {
 
     use image::{ImageBuffer, Rgb};
-    use crate::{ProcessingConfig, VideoDerivativeProcessor, VideoFrame};
+    use crate::{DerivativeIntegrator, FrameGenerator, ProcessingConfig, VideoDerivativeProcessor, VideoFrame};
 
     #[test]
     fn test_video_frame_creation() {
@@ -64,4 +72,1102 @@ mod tests {
         assert_eq!(config.output_height, 480);
         assert!(config.noise_reduction);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_signed_derivative_roundtrip_is_lossless() {
+        let frames = FrameGenerator::new(160, 120, 30).generate_complex_scene_frames(12);
+        let config = ProcessingConfig {
+            noise_reduction: false,
+            keyframe_interval: 5,
+            ..ProcessingConfig::default()
+        };
+        let mut processor = VideoDerivativeProcessor::new(config.clone());
+
+        let packets: Vec<_> = frames.iter().map(|f| processor.process_frame_signed(f)).collect();
+        assert!(packets[0].is_keyframe());
+        assert!(packets[5].is_keyframe());
+        assert!(!packets[6].is_keyframe());
+
+        let restored = DerivativeIntegrator::reconstruct(&packets).unwrap();
+        assert_eq!(restored.len(), frames.len());
+        for (original, restored) in frames.iter().zip(&restored) {
+            assert_eq!(original.data, restored.data);
+        }
+
+        // Точность гарантируется только для целых форматов
+        let frames: Vec<VideoFrame<Rgb<u16>>> = (0..8)
+            .map(|i| VideoFrame::from_buffer(ImageBuffer::from_fn(16, 16, |x, y| Rgb([(x * 4000 + i * 7919) as u16, (y * 3001) as u16, (i * 65535 / 7) as u16])), i as usize, i as f64 / 25.0))
+            .collect();
+        let mut processor = VideoDerivativeProcessor::<Rgb<u16>>::for_format(config);
+        let packets: Vec<_> = frames.iter().map(|f| processor.process_frame_signed(f)).collect();
+        let restored = DerivativeIntegrator::reconstruct(&packets).unwrap();
+        for (original, restored) in frames.iter().zip(&restored) {
+            assert_eq!(original.data, restored.data);
+        }
+    }
+
+    #[test]
+    fn test_integrator_reanchoring_bounds_drift() {
+        // Яркость растет на 1 за кадр - ниже порога, поэтому разницы обнуляются
+        let frames: Vec<_> = (0..20)
+            .map(|i| VideoFrame::new(ImageBuffer::from_pixel(4, 4, Rgb([100 + i as u8, 0, 0])), i, i as f64 / 30.0))
+            .collect();
+        let config = ProcessingConfig { keyframe_interval: 4, ..ProcessingConfig::default() };
+        let mut processor = VideoDerivativeProcessor::new(config);
+        let mut integrator = DerivativeIntegrator::new();
+
+        for frame in &frames {
+            let restored = integrator.push(&processor.process_frame_signed(frame)).unwrap();
+            let error = restored.data.get_pixel(0, 0)[0].abs_diff(frame.data.get_pixel(0, 0)[0]);
+            assert!(error < 4);
+        }
+    }
+
+    #[test]
+    fn test_integrator_requires_reference() {
+        let derivative = crate::SignedDerivative::<Rgb<u8>>::new(vec![0; 12], 2, 2, 1, 0.0);
+        assert!(DerivativeIntegrator::new().integrate(&derivative).is_err());
+    }
+
+    #[test]
+    fn test_delta_container_roundtrip_and_seek() {
+        use crate::{DeltaFileReader, DeltaFileWriter, DeltaHeader};
+        use std::io::Cursor;
+
+        let frames = FrameGenerator::new(160, 120, 30).generate_complex_scene_frames(10);
+        let config = ProcessingConfig { noise_reduction: false, keyframe_interval: 4, ..ProcessingConfig::default() };
+        let mut processor = VideoDerivativeProcessor::new(config);
+
+        let mut writer = DeltaFileWriter::new(Cursor::new(Vec::new()), DeltaHeader::new(160, 120, 30)).unwrap();
+        for frame in &frames {
+            writer.write_packet(&processor.process_frame_signed(frame)).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+        assert!(bytes.len() < frames.len() * 160 * 120 * 3);
+
+        let mut reader = DeltaFileReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.len(), frames.len());
+        assert_eq!(reader.header().width, 160);
+        assert_eq!(reader.frame_at(7).unwrap().data, frames[7].data);
+
+        let restored = DerivativeIntegrator::reconstruct(
+            &reader.packets().collect::<Result<Vec<_>, _>>().unwrap()
+        ).unwrap();
+        assert_eq!(restored.last().unwrap().data, frames.last().unwrap().data);
+    }
+
+    #[test]
+    fn test_grayscale_frame_difference() {
+        use image::Luma;
+
+        let config = ProcessingConfig::default();
+        let mut processor = VideoDerivativeProcessor::<Luma<u8>>::for_format(config);
+
+        processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(8, 8, Luma([10u8])), 0, 0.0));
+        let derivative = processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(8, 8, Luma([200u8])), 1, 0.04));
+        assert_eq!(derivative.data.get_pixel(3, 3)[0], 190);
+        assert_eq!(derivative.pixel_format(), crate::PixelFormat::Luma8);
+    }
+
+    #[test]
+    fn test_high_bit_depth_threshold_scaling() {
+        use image::{Luma, Rgba};
+
+        // Порог 30 в 8-битных единицах соответствует 7710 в 16-битных
+        let mut processor = VideoDerivativeProcessor::<Rgb<u16>>::for_format(ProcessingConfig::default());
+        processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(4, 4, Rgb([1000u16, 1000, 1000])), 0, 0.0));
+        let small = processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(4, 4, Rgb([5000u16, 5000, 5000])), 1, 0.0));
+        assert_eq!(small.data.get_pixel(0, 0)[0], 0);
+        let large = processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(4, 4, Rgb([40000u16, 5000, 5000])), 2, 0.0));
+        assert_eq!(large.data.get_pixel(0, 0)[0], 35000);
+
+        let mut processor = VideoDerivativeProcessor::<Luma<f32>>::for_format(ProcessingConfig::default());
+        processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(4, 4, Luma([0.25f32])), 0, 0.0));
+        let derivative = processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(4, 4, Luma([0.75f32])), 1, 0.0));
+        assert!((derivative.data.get_pixel(1, 1)[0] - 0.5).abs() < 1e-6);
+        // Как и целые форматы, f32 насыщается в 0.0..=1.0
+        use crate::types::Sample;
+        assert_eq!((f32::from_unit(1.5), f32::from_unit(-0.5)), (1.0, 0.0));
+        assert_eq!((u8::from_unit(1.5), u16::from_unit(-0.5)), (255, 0));
+
+        // Альфа-канал производной остается непрозрачным
+        let mut processor = VideoDerivativeProcessor::<Rgba<u8>>::for_format(ProcessingConfig::default());
+        let derivative = processor.process_frame(&VideoFrame::from_buffer(ImageBuffer::from_pixel(2, 2, Rgba([1u8, 2, 3, 0])), 0, 0.0));
+        assert_eq!(derivative.data.get_pixel(0, 0)[3], 255);
+    }
+
+    #[test]
+    fn test_16bit_delta_container_is_lossless() {
+        use crate::{DeltaFileReader, DeltaFileWriter, DeltaHeader};
+        use image::Luma;
+        use std::io::Cursor;
+
+        let frames: Vec<VideoFrame<Luma<u16>>> = (0..6)
+            .map(|i| VideoFrame::from_buffer(ImageBuffer::from_fn(16, 16, |x, y| Luma([(x * 1000 + y * 37 + i * 4099) as u16])), i as usize, i as f64 / 25.0))
+            .collect();
+        let config = ProcessingConfig { noise_reduction: false, keyframe_interval: 0, ..ProcessingConfig::default() };
+        let mut processor = VideoDerivativeProcessor::for_format(config);
+
+        let mut writer = DeltaFileWriter::new(Cursor::new(Vec::new()), DeltaHeader::new(16, 16, 25)).unwrap();
+        for frame in &frames {
+            writer.write_packet(&processor.process_frame_signed(frame)).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+
+        assert!(DeltaFileReader::<_, Rgb<u8>>::new(Cursor::new(bytes.clone())).is_err());
+
+        // Поврежденные счетчики отклоняются до выделения памяти под них
+        let index_offset = u64::from_le_bytes(bytes[bytes.len() - 12..bytes.len() - 4].try_into().unwrap()) as usize;
+        let mut corrupted = bytes.clone();
+        corrupted[index_offset..index_offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(DeltaFileReader::<_, Luma<u16>>::new(Cursor::new(corrupted)), Err(crate::Error::Decode(_))));
+        let mut corrupted = bytes.clone();
+        corrupted[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = DeltaFileReader::<_, Luma<u16>>::new(Cursor::new(corrupted)).unwrap();
+        assert!(matches!(reader.read_packet(0), Err(crate::Error::Decode(_))));
+        let mut corrupted = bytes.clone();
+        corrupted[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(DeltaFileReader::<_, Luma<u16>>::new(Cursor::new(corrupted)), Err(crate::Error::Decode(_))));
+
+        // Пропуск нулей около u64::MAX не должен переполнять позицию в кадре
+        let delta_offset = DeltaFileReader::<_, Luma<u16>>::new(Cursor::new(bytes.clone())).unwrap().index()[1].offset as usize;
+        let mut corrupted = bytes.clone();
+        let payload = delta_offset + 21;
+        corrupted[payload..payload + 11].copy_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x01]);
+        let mut reader = DeltaFileReader::<_, Luma<u16>>::new(Cursor::new(corrupted)).unwrap();
+        assert!(matches!(reader.read_packet(1), Err(crate::Error::Decode(_))));
+
+        let mut reader = DeltaFileReader::<_, Luma<u16>>::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.header().pixel_format, crate::PixelFormat::Luma16);
+        assert_eq!(reader.frame_at(5).unwrap().data, frames[5].data);
+    }
+
+    #[test]
+    fn test_difference_backends_agree() {
+        use crate::DifferenceBackend;
+
+        let frames = FrameGenerator::new(97, 61, 30).generate_moving_object_frames(3);
+        let results: Vec<_> = [DifferenceBackend::PerPixel, DifferenceBackend::Slice, DifferenceBackend::Parallel]
+            .into_iter()
+            .map(|backend| {
+                let mut processor = VideoDerivativeProcessor::new(ProcessingConfig { backend, ..ProcessingConfig::default() });
+                frames.iter().map(|f| processor.process_frame(f).data).collect::<Vec<_>>()
+            })
+            .collect();
+
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0], results[2]);
+    }
+
+    #[test]
+    fn test_streaming_pipeline_matches_batch() {
+        use crate::video::{process_stream, process_stream_threaded, IterSource, VideoSource};
+
+        let frames = FrameGenerator::new(160, 120, 30).generate_moving_object_frames(8);
+        let mut batch_processor = VideoDerivativeProcessor::new(ProcessingConfig::default());
+        let batch: Vec<_> = frames.iter().map(|f| batch_processor.process_frame(f)).collect();
+
+        let mut source = IterSource::new(frames.clone());
+        assert_eq!(source.frame_count_hint(), Some(8));
+        let mut processor = VideoDerivativeProcessor::new(ProcessingConfig::default());
+        let mut streamed: Vec<VideoFrame> = Vec::new();
+        assert_eq!(process_stream(&mut source, &mut processor, &mut streamed).unwrap(), 8);
+
+        let mut processor = VideoDerivativeProcessor::new(ProcessingConfig::default());
+        let mut threaded: Vec<VideoFrame> = Vec::new();
+        process_stream_threaded(IterSource::new(frames), &mut processor, &mut threaded, 2).unwrap();
+
+        for ((a, b), c) in batch.iter().zip(&streamed).zip(&threaded) {
+            assert_eq!(a.data, b.data);
+            assert_eq!(a.data, c.data);
+        }
+    }
+
+    #[test]
+    fn test_probe_json_parsing() {
+        use crate::video::MediaInfo;
+
+        let json = r#"{
+            "streams": [
+                {
+                    "index": 0, "codec_type": "video", "codec_name": "hevc",
+                    "width": 1920, "height": 1080, "pix_fmt": "yuv420p10le",
+                    "r_frame_rate": "60000/1001", "avg_frame_rate": "30000/1001",
+                    "duration": "10.010000",
+                    "side_data_list": [{ "side_data_type": "Display Matrix", "rotation": -90 }]
+                },
+                {
+                    "index": 1, "codec_type": "audio", "codec_name": "aac",
+                    "sample_rate": "48000", "channels": 2, "channel_layout": "stereo", "bit_rate": "128000"
+                }
+            ],
+            "format": { "format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "10.026667", "bit_rate": "8000000" }
+        }"#;
+
+        let info = MediaInfo::from_ffprobe_json(json).unwrap();
+        let video = info.video.as_ref().unwrap();
+        assert_eq!(video.codec, "hevc");
+        assert_eq!(video.bit_depth, Some(10));
+        assert_eq!(video.rotation, 270);
+        assert_eq!(video.display_dimensions(), (1080, 1920));
+        assert!((video.effective_frame_rate().unwrap() - 29.97).abs() < 0.01);
+        assert!(video.is_variable_frame_rate());
+        assert_eq!(video.frame_count, Some(300));
+        assert_eq!(info.audio_streams[0].sample_rate, Some(48000));
+        assert!(info.has_audio());
+    }
+
+    #[test]
+    fn test_showinfo_pts_and_vfr_concat_list() {
+        use crate::video::timestamps::{is_uniform, parse_showinfo_frame, parse_showinfo_pts, spawn_pts_reader, spawn_stderr_tail, write_ffconcat};
+
+        let line = "[Parsed_showinfo_0 @ 0x55d0c8c0] n:   3 pts:   3003 pts_time:0.100100 duration:   1001 fmt:yuv420p";
+        assert_eq!(parse_showinfo_pts(line), Some(0.1001));
+        assert_eq!(parse_showinfo_frame(line), Some((3, 0.1001)));
+
+        // Метки приходят с номерами кадров, прочие строки остаются для диагностики
+        let log = format!("{}\nError while decoding\n", line);
+        let (pts, stderr) = spawn_pts_reader(std::io::Cursor::new(log));
+        assert_eq!(stderr.join().unwrap(), ["Error while decoding"]);
+        assert_eq!(pts.try_iter().collect::<Vec<_>>(), [(3, 0.1001)]);
+        assert_eq!(parse_showinfo_pts("Stream #0:0: Video: h264, pts_time:1.0"), None);
+
+        // Для ошибки кодировщика сохраняются только последние 20 строк
+        let log: String = (1..=25).map(|i| format!("line {}\n", i)).collect();
+        let tail = spawn_stderr_tail(std::io::Cursor::new(log)).join().unwrap();
+        assert_eq!(tail.len(), 20);
+        assert_eq!((tail[0].as_str(), tail[19].as_str()), ("line 6", "line 25"));
+
+        assert!(is_uniform(&[0.0, 1.0 / 30.0, 2.0 / 30.0, 3.0 / 30.0]));
+        assert!(!is_uniform(&[0.0, 0.033, 0.050, 0.120]));
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let list = dir.path().join("list.ffconcat");
+        let files: Vec<_> = ["a.png", "b.png", "c.png"].iter().map(|f| dir.path().join(f)).collect();
+        write_ffconcat(&list, &files, &[0.0, 0.04, 0.1], 25.0).unwrap();
+
+        let text = std::fs::read_to_string(&list).unwrap();
+        let durations: Vec<&str> = text.lines().filter(|l| l.starts_with("duration")).collect();
+        assert_eq!(durations, ["duration 0.040000", "duration 0.060000", "duration 0.060000"]);
+        assert!(text.trim_end().ends_with("c.png'"));
+    }
+
+    #[test]
+    fn test_time_normalized_derivative_is_frame_rate_independent() {
+        use crate::TimeNormalization;
+
+        // Яркость растет со скоростью 600 единиц в секунду при 30 и 60 к/с
+        let make_frames = |fps: u32| -> Vec<VideoFrame> {
+            (0..3)
+                .map(|i| {
+                    let value = (i as f64 * 600.0 / fps as f64) as u8;
+                    VideoFrame::new(ImageBuffer::from_pixel(4, 4, Rgb([value, value, value])), i, i as f64 / fps as f64)
+                })
+                .collect()
+        };
+        let config = ProcessingConfig {
+            noise_reduction: false,
+            time_normalization: TimeNormalization::PerSecond { scale: 1.0 / 30.0 },
+            ..ProcessingConfig::default()
+        };
+
+        let mut results = Vec::new();
+        for fps in [30, 60] {
+            let mut processor = VideoDerivativeProcessor::new(config.clone());
+            let derivatives: Vec<_> = make_frames(fps).iter().map(|f| processor.process_frame(f)).collect();
+            results.push(derivatives[2].data.get_pixel(0, 0)[0]);
+        }
+
+        assert_eq!(results[0], 20);
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn test_audio_track_arguments() {
+        use crate::video::{AudioMode, AudioTrack};
+
+        let track = AudioTrack::new("in.mov", AudioMode::Copy);
+        assert_eq!(track.input_args(12.5), ["-ss", "12.500000", "-i", "in.mov"]);
+        assert_eq!(track.input_args(0.0), ["-i", "in.mov"]);
+        assert_eq!(
+            track.output_args(1),
+            ["-map", "0:v:0", "-map", "1:a?", "-c:a", "copy", "-shortest"]
+        );
+
+        let trimmed = AudioTrack::new("in.mov", AudioMode::aac()).with_range(Some(3.0), Some(30.0));
+        assert_eq!(trimmed.input_args(99.0), ["-ss", "3.000000", "-t", "30.000000", "-i", "in.mov"]);
+        assert!(trimmed.output_args(1).contains(&"aac".to_string()));
+        assert!(!AudioTrack::new("in.mov", AudioMode::None).is_enabled());
+        assert_eq!("copy".parse::<AudioMode>().unwrap(), AudioMode::Copy);
+        assert_eq!("AAC".parse::<AudioMode>().unwrap(), AudioMode::aac());
+        assert!("mp3".parse::<AudioMode>().is_err());
+        assert!(":128k".parse::<AudioMode>().is_err());
+        // Display и FromStr понимают друг друга
+        let modes = [
+            AudioMode::None,
+            AudioMode::Copy,
+            AudioMode::aac(),
+            AudioMode::Reencode { codec: "aac".into(), bitrate: Some("128k".into()) },
+            AudioMode::Reencode { codec: "libopus".into(), bitrate: None },
+        ];
+        for mode in modes {
+            assert_eq!(mode.to_string().parse::<AudioMode>().unwrap(), mode);
+        }
+        assert_eq!(AudioMode::Reencode { codec: "aac".into(), bitrate: Some("128k".into()) }.to_string(), "aac:128k");
+    }
+
+    #[test]
+    fn test_encoder_arguments() {
+        use crate::video::{EncoderConfig, EncoderPixelFormat, VideoCodec};
+
+        assert_eq!(
+            EncoderConfig::default().to_args(),
+            ["-c:v", "libx264", "-crf", "23", "-pix_fmt", "yuv420p"]
+        );
+
+        let lossless = EncoderConfig::lossless(VideoCodec::H264)
+            .with_pixel_format(EncoderPixelFormat::Gray)
+            .to_args();
+        assert_eq!(lossless, ["-c:v", "libx264", "-qp", "0", "-pix_fmt", "gray"]);
+
+        // Без потерь по умолчанию кадры не переводятся в YUV
+        let lossless = EncoderConfig::lossless(VideoCodec::H264).to_args();
+        assert_eq!(lossless, ["-c:v", "libx264rgb", "-qp", "0", "-pix_fmt", "bgr0"]);
+        for codec in [VideoCodec::H265, VideoCodec::Vp9, VideoCodec::Av1] {
+            assert_eq!(EncoderConfig::lossless(codec).effective_pixel_format(), EncoderPixelFormat::Gbrp);
+        }
+
+        let vp9 = EncoderConfig::new(VideoCodec::Vp9).with_crf(31).with_preset("good").to_args();
+        assert_eq!(vp9, ["-c:v", "libvpx-vp9", "-crf", "31", "-b:v", "0", "-deadline", "good", "-pix_fmt", "yuv420p"]);
+
+        let ffv1 = EncoderConfig::new(VideoCodec::Ffv1).with_container("mkv");
+        assert!(ffv1.validate().is_ok());
+        assert!(ffv1.to_args().ends_with(&["-pix_fmt".to_string(), "gbrp".into(), "-f".into(), "matroska".into()]));
+        let y4m = EncoderConfig::default().with_container("y4m");
+        assert!(y4m.validate().is_ok());
+        assert!(y4m.to_args().ends_with(&["-f".to_string(), "yuv4mpegpipe".into()]));
+        assert!(EncoderConfig::default().with_container("mp5").validate().is_err());
+
+        // У ProRes своя шкала квантователя вместо CRF
+        let prores = EncoderConfig::new(VideoCodec::ProRes).with_quality(4).with_preset("hq");
+        assert!(prores.validate().is_ok());
+        assert_eq!(prores.to_args(), ["-c:v", "prores_ks", "-qscale:v", "4", "-profile:v", "hq", "-pix_fmt", "yuv422p10le"]);
+        assert!(EncoderConfig::new(VideoCodec::ProRes).validate().is_ok());
+        assert!(EncoderConfig::new(VideoCodec::ProRes).with_crf(20).validate().is_err());
+        assert!(EncoderConfig::new(VideoCodec::ProRes).with_quality(40).validate().is_err());
+        assert!(EncoderConfig::default().with_quality(4).validate().is_err());
+        assert!(EncoderConfig::lossless(VideoCodec::ProRes).validate().is_err());
+        assert!(EncoderConfig::default().with_crf(60).validate().is_err());
+        assert_eq!("hevc".parse::<VideoCodec>().unwrap(), VideoCodec::H265);
+    }
+
+    #[test]
+    fn test_y4m_roundtrip_and_pipeline_without_ffmpeg() {
+        use crate::video::{VideoProcessor, VideoSink, VideoSource, Y4mColorspace, Y4mReader, Y4mWriter};
+
+        let frames = FrameGenerator::new(160, 120, 30).generate_moving_object_frames(3);
+
+        // 4:4:4 почти без потерь; у 4:2:0 ошибки только на цветовых границах
+        for (colorspace, max_tolerance, mean_tolerance) in
+            [(Y4mColorspace::C444, 2, 1.0), (Y4mColorspace::C420, 255, 3.0)]
+        {
+            let mut writer = Y4mWriter::new(Vec::new(), 30).with_colorspace(colorspace);
+            for frame in &frames {
+                writer.write_frame(frame).unwrap();
+            }
+            let bytes = writer.into_inner().unwrap();
+
+            let mut reader = Y4mReader::new(&bytes[..]).unwrap();
+            assert_eq!((reader.header().width, reader.header().height), (160, 120));
+            for original in &frames {
+                let decoded = reader.next_frame().unwrap().unwrap();
+                let errors: Vec<u8> = original
+                    .data
+                    .as_raw()
+                    .iter()
+                    .zip(decoded.data.as_raw())
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .collect();
+                let mean = errors.iter().map(|&e| e as f64).sum::<f64>() / errors.len() as f64;
+                assert!(*errors.iter().max().unwrap() <= max_tolerance, "{:?}", colorspace);
+                assert!(mean <= mean_tolerance, "{:?}: {}", colorspace, mean);
+            }
+            assert!(reader.next_frame().unwrap().is_none());
+        }
+
+        // Полный цикл Y4M -> производная -> Y4M работает без FFmpeg
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.y4m");
+        let output = dir.path().join("out.y4m");
+        let mut writer = Y4mWriter::create(&input, 30).unwrap().with_colorspace(Y4mColorspace::C444);
+        for frame in &frames {
+            writer.write_frame(frame).unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+
+        let processor = VideoProcessor::new().unwrap();
+        // Медленный путь через PNG для VFR-видео включается только явно
+        assert!(!processor.preserves_timestamps());
+        processor
+            .create_simple_derivative_video(input.to_str().unwrap(), output.to_str().unwrap())
+            .unwrap();
+        let derived = Y4mReader::open(&output).unwrap().frames().count();
+        assert_eq!(derived, 3);
+    }
+
+    #[test]
+    fn test_fractional_frame_rates_are_kept_exact() {
+        use crate::video::{FrameRange, FrameRate, VideoProcessor, VideoSink, Y4mReader, Y4mWriter};
+
+        let ntsc = FrameRate::new(60000, 1001);
+        assert_eq!(FrameRate::from_f64(60000.0 / 1001.0), Some(ntsc));
+        assert_eq!(FrameRate::from_f64(29.97002997002997), Some(FrameRate::new(30000, 1001)));
+        assert_eq!(FrameRate::from_f64(25.0), Some(FrameRate::from(25)));
+        assert_eq!(FrameRate::from_f64(0.0), None);
+        assert_eq!(ntsc.to_string(), "60000/1001");
+        assert_eq!(FrameRate::from(25).divided_by(3).to_string(), "25/3");
+        assert_eq!(FrameRate::new(30000, 1001).divided_by(2), FrameRate::new(15000, 1001));
+
+        // Частота входа и прореживание доходят до выхода без округления
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.y4m");
+        let mut writer = Y4mWriter::create(&input, 60).unwrap().with_frame_rate(60000, 1001);
+        for frame in FrameGenerator::new(160, 120, 30).generate_moving_object_frames(6) {
+            writer.write_frame(&frame).unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+
+        for (step, expected) in [(1, (60000, 1001)), (3, (20000, 1001))] {
+            let output = dir.path().join(format!("out_{}.y4m", step));
+            let processor = VideoProcessor::new().unwrap().with_range(FrameRange::default().every_nth(step));
+            let fps = processor.detect_frame_rate(input.to_str().unwrap()).unwrap();
+            assert_eq!(fps, ntsc);
+            processor
+                .create_derivative_video(input.to_str().unwrap(), output.to_str().unwrap(), fps, 25)
+                .unwrap();
+            assert_eq!(Y4mReader::open(&output).unwrap().header().frame_rate, expected);
+        }
+    }
+
+    #[test]
+    fn test_image_sequence_roundtrip() {
+        use crate::video::{ImageSequenceSink, ImageSequenceSource, VideoProcessor, VideoSink, VideoSource};
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames = FrameGenerator::new(160, 120, 30).generate_moving_object_frames(11);
+        // Номера без ведущих нулей: img_10 должен идти после img_9
+        for (i, frame) in frames.iter().enumerate() {
+            frame.save_to_file(&dir.path().join(format!("img_{}.png", i))).unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "не кадр").unwrap();
+
+        let pattern = dir.path().join("img_*.png");
+        let mut source = ImageSequenceSource::open(pattern.to_str().unwrap(), 1000.0).unwrap();
+        assert_eq!(source.frame_count_hint(), Some(11));
+        for original in &frames {
+            let frame = source.next_frame().unwrap().unwrap();
+            assert_eq!(frame.data, original.data);
+            assert!((frame.timestamp - original.frame_number as f64 / 1000.0).abs() < 1e-9);
+        }
+
+        let output = dir.path().join("out/diff_%03d.png");
+        let mut sink = ImageSequenceSink::create(output.to_str().unwrap()).unwrap();
+        sink.write_frame(&frames[0]).unwrap();
+        assert!(dir.path().join("out/diff_001.png").exists());
+
+        // Директория целиком -> последовательность производных без FFmpeg
+        let derived = dir.path().join("derived");
+        VideoProcessor::new()
+            .unwrap()
+            .create_derivative_video(
+                pattern.to_str().unwrap(),
+                &format!("{}/", derived.display()),
+                1000,
+                25,
+            )
+            .unwrap();
+        assert_eq!(std::fs::read_dir(&derived).unwrap().count(), 11);
+        assert!(derived.join("frame_000011.png").exists());
+    }
+
+    #[test]
+    fn test_animated_gif_and_apng_sinks() {
+        use crate::video::{ApngSink, GifSink, VideoSink};
+        use image::AnimationDecoder;
+
+        let mut frames = FrameGenerator::new(160, 120, 30).generate_complex_scene_frames(3);
+        // Второй кадр длится вдвое дольше: длительности берутся из меток времени
+        frames[2].timestamp = 0.1;
+
+        let mut gif = Vec::new();
+        let mut sink = GifSink::new(&mut gif, 30).with_dithering(true);
+        for frame in &frames {
+            sink.write_frame(frame).unwrap();
+        }
+        sink.finish().unwrap();
+        drop(sink);
+
+        let decoded = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        let delays: Vec<u32> = decoded.iter().map(|f| f.delay().numer_denom_ms().0).collect();
+        assert_eq!(delays, [30, 70, 30]);
+
+        let mut apng = Vec::new();
+        let mut sink = ApngSink::new(&mut apng, 30);
+        for frame in &frames {
+            sink.write_frame(frame).unwrap();
+        }
+        sink.finish().unwrap();
+        drop(sink);
+
+        let mut reader = png::Decoder::new(std::io::Cursor::new(apng)).read_info().unwrap();
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 3);
+        let mut buffer = vec![0; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut buffer).unwrap();
+        assert_eq!(&buffer[..], frames[0].data.as_raw().as_slice());
+    }
+
+    #[test]
+    fn test_composite_layouts() {
+        use crate::CompositeLayout;
+
+        let original = VideoFrame::new(ImageBuffer::from_pixel(4, 2, Rgb([100, 100, 100])), 1, 0.5);
+        let mut derivative = VideoFrame::new(ImageBuffer::new(4, 2), 1, 0.5);
+        derivative.data.put_pixel(1, 1, Rgb([0, 255, 40]));
+
+        let grid = CompositeLayout::Grid.compose(&original, derivative.clone());
+        assert_eq!(grid.dimensions(), CompositeLayout::Grid.output_dimensions(4, 2));
+        assert_eq!(grid.dimensions(), (8, 4));
+        assert_eq!(grid.data.get_pixel(0, 0), &Rgb([100, 100, 100]));
+        assert_eq!(grid.data.get_pixel(5, 1), &Rgb([0, 255, 40]));
+        // Маска в левом нижнем углу, тепловая карта максимума - белая
+        assert_eq!(grid.data.get_pixel(1, 3), &Rgb([255, 255, 255]));
+        assert_eq!(grid.data.get_pixel(0, 3), &Rgb([0, 0, 0]));
+        assert_eq!(grid.data.get_pixel(5, 3), &Rgb([255, 255, 255]));
+        assert_eq!((grid.frame_number, grid.timestamp), (1, 0.5));
+
+        let stacked = CompositeLayout::Stacked.compose(&original, derivative.clone());
+        assert_eq!(stacked.dimensions(), (4, 4));
+
+        let overlay = "overlay".parse::<CompositeLayout>().unwrap().compose(&original, derivative);
+        assert_eq!(overlay.data.get_pixel(0, 0), &Rgb([100, 100, 100]));
+        assert_eq!(overlay.data.get_pixel(1, 1), &Rgb([193, 40, 40]));
+    }
+
+    #[test]
+    fn test_region_detection_tracking_and_annotation() {
+        use crate::processors::RegionDetector;
+        use crate::MotionAnnotator;
+
+        let square = |x: u32, frame_number: usize| {
+            let mut data = ImageBuffer::new(120, 80);
+            for py in 40..50 {
+                for px in x..x + 10 {
+                    data.put_pixel(px, py, Rgb([200, 200, 200]));
+                }
+            }
+            // Одиночный шумовой пиксель меньше min_area
+            data.put_pixel(2, 78, Rgb([255, 255, 255]));
+            VideoFrame::new(data, frame_number, frame_number as f64 / 10.0)
+        };
+
+        let regions = RegionDetector::default().detect(&square(30, 0).data);
+        assert_eq!(regions.len(), 1);
+        assert_eq!((regions[0].x, regions[0].y, regions[0].width, regions[0].height), (30, 40, 10, 10));
+        assert_eq!(regions[0].area, 100);
+        assert_eq!(regions[0].centroid, (34.5, 44.5));
+
+        // Цепочка близких квадратов сливается в одну рамку, дальний остается отдельным
+        let mut chain = image::RgbImage::new(200, 80);
+        for x0 in [10, 25, 40, 55, 150] {
+            for py in 20..30 {
+                for px in x0..x0 + 10 {
+                    chain.put_pixel(px, py, Rgb([200, 200, 200]));
+                }
+            }
+        }
+        let regions = RegionDetector::default().detect(&chain);
+        assert_eq!(regions.len(), 2);
+        assert_eq!((regions[0].x, regions[0].width, regions[0].area), (10, 55, 400));
+        assert_eq!((regions[1].x, regions[1].width, regions[1].area), (150, 10, 100));
+
+        let mut annotator = MotionAnnotator::default();
+        let mut ids = Vec::new();
+        for (frame_number, x) in [30, 40, 50].into_iter().enumerate() {
+            let derivative = square(x, frame_number);
+            let mut target = VideoFrame::new(ImageBuffer::new(120, 80), frame_number, derivative.timestamp);
+            assert_eq!(annotator.process(&mut target, &derivative).len(), 1);
+            ids.extend(annotator.tracker.tracks().iter().map(|t| t.id));
+
+            // Рамка трека по краю области и подпись кадра в углу
+            assert_ne!(target.data.get_pixel(x, 45), &Rgb([0, 0, 0]));
+            assert_ne!(target.data.get_pixel(2, 2), &Rgb([0, 0, 0]));
+        }
+        assert_eq!(ids, vec![1, 1, 1]);
+        let track = &annotator.tracker.tracks()[0];
+        assert_eq!(track.trail.len(), 3);
+        assert_eq!(track.last_seen().unwrap().x, 54.5);
+    }
+
+    #[test]
+    fn test_frame_range_selection() {
+        use crate::video::{parse_time, FrameRange, IterSource, RangedSource, VideoSource};
+
+        assert_eq!(parse_time("90.5").unwrap(), 90.5);
+        assert_eq!(parse_time("1:05:00").unwrap(), 3900.0);
+        assert_eq!(parse_time("02:03.25").unwrap(), 123.25);
+        assert!(parse_time("1:75").is_err());
+        assert!(parse_time("abc").is_err());
+
+        let range = FrameRange::new().starting_at(1.0).with_duration(2.0).every_nth(3);
+        assert!(range.validate().is_ok());
+        assert_eq!(range.end_seconds(), Some(3.0));
+        assert_eq!(range.input_args(), vec!["-ss", "1.000000", "-t", "2.000000"]);
+        assert_eq!(range.filter(), "select=not(mod(n\\,3)),showinfo");
+        assert_eq!(range.frame_count_hint(Some(100), 10.0), Some(7));
+        assert!(FrameRange::new().starting_at(5.0).ending_at(4.0).validate().is_err());
+        assert!(FrameRange::new().ending_at(4.0).with_duration(1.0).validate().is_err());
+        assert!(FrameRange::new().every_nth(0).validate().is_err());
+
+        // 10 кадров в секунду: фрагмент [1, 3) с шагом 3 - кадры 1.0, 1.3, ..., 2.8
+        let frames = FrameGenerator::new(160, 120, 10).generate_moving_object_frames(50);
+        let selected: Vec<_> = RangedSource::new(IterSource::new(frames.clone()), range)
+            .frames()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let timestamps: Vec<f64> = selected.iter().map(|f| (f.timestamp * 10.0).round() / 10.0).collect();
+        assert_eq!(timestamps, vec![1.0, 1.3, 1.6, 1.9, 2.2, 2.5, 2.8]);
+        assert_eq!(selected.iter().map(|f| f.frame_number).collect::<Vec<_>>(), (1..=7).collect::<Vec<_>>());
+
+        let limited = RangedSource::new(IterSource::new(frames), FrameRange::new().with_max_frames(4));
+        assert_eq!(limited.frame_count_hint(), Some(4));
+        assert_eq!(limited.frames().count(), 4);
+    }
+
+    #[test]
+    fn test_resize_and_crop_geometry() {
+        use crate::types::{CropRect, GeometryStage};
+        use crate::video::{process_stream_with, IterSource};
+        use crate::{CompositeLayout, GeometryConfig, ResizeMode};
+
+        let target = (64, 64);
+        let dimensions = |resize| GeometryConfig { resize, ..GeometryConfig::default() }.output_dimensions(160, 120, target);
+        assert_eq!(dimensions(ResizeMode::None), (160, 120));
+        assert_eq!(dimensions(ResizeMode::Fit), (64, 48));
+        assert_eq!(dimensions(ResizeMode::Fill), (64, 64));
+        assert_eq!(dimensions(ResizeMode::Stretch), (64, 64));
+        assert_eq!(dimensions(ResizeMode::Pad), (64, 64));
+        assert_eq!("100:50:10:20".parse::<CropRect>().unwrap(), CropRect::new(10, 20, 100, 50));
+        assert!("0:50".parse::<CropRect>().is_err());
+
+        // Белый кадр 160x120: при вписывании в квадрат остаются черные поля
+        let white = VideoFrame::new(ImageBuffer::from_pixel(160, 120, Rgb([255, 255, 255])), 3, 0.1);
+        let pad = GeometryConfig { resize: ResizeMode::Pad, ..GeometryConfig::default() };
+        let padded = pad.apply(&white, target);
+        assert_eq!(padded.dimensions(), target);
+        assert_eq!(padded.data.get_pixel(32, 2), &Rgb([0, 0, 0]));
+        assert_eq!(padded.data.get_pixel(32, 32), &Rgb([255, 255, 255]));
+        assert_eq!((padded.frame_number, padded.timestamp), (3, 0.1));
+
+        let frames = FrameGenerator::new(160, 120, 30).generate_moving_object_frames(4);
+        for stage in [GeometryStage::BeforeDifference, GeometryStage::AfterDifference] {
+            let config = ProcessingConfig {
+                output_width: 80,
+                output_height: 80,
+                geometry: GeometryConfig {
+                    resize: ResizeMode::Fit,
+                    crop: Some(CropRect::new(0, 0, 120, 120)),
+                    stage,
+                    ..GeometryConfig::default()
+                },
+                ..ProcessingConfig::default()
+            };
+            let mut processor = VideoDerivativeProcessor::new(config);
+            let mut output = Vec::new();
+            let count = process_stream_with(&mut IterSource::new(frames.clone()), &mut processor, &mut output, |original, derivative| {
+                CompositeLayout::SideBySide.compose(original, derivative)
+            })
+            .unwrap();
+            assert_eq!(count, 4);
+            assert!(output.iter().all(|frame| frame.dimensions() == (160, 80)));
+        }
+    }
+
+    #[test]
+    fn test_live_raw_stream_from_growing_file_with_events() {
+        use crate::processors::{MotionEventDetector, MotionEventKind};
+        use crate::video::{LiveConfig, LiveFormat, LiveInput, VideoProcessor, VideoSource, Y4mReader};
+        use std::io::Write;
+        use std::time::Duration;
+
+        // Пять статичных кадров, пять с движущимся объектом, снова пять статичных
+        let moving = FrameGenerator::new(160, 120, 10).generate_moving_object_frames(5);
+        let still = moving[0].data.clone();
+        let mut raw = Vec::new();
+        for i in 0..15 {
+            let data = if (5..10).contains(&i) { &moving[i - 5].data } else { &still };
+            raw.push(data.as_raw().clone());
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("recording.rgb");
+        let output = dir.path().join("live.y4m");
+        std::fs::File::create(&input).unwrap();
+
+        // Запись идет параллельно с чтением: кадры дописываются частями
+        let path = input.clone();
+        let recorder = std::thread::spawn(move || {
+            let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+            for frame in raw {
+                let (head, tail) = frame.split_at(frame.len() / 2);
+                file.write_all(head).unwrap();
+                file.flush().unwrap();
+                std::thread::sleep(Duration::from_millis(5));
+                file.write_all(tail).unwrap();
+            }
+        });
+
+        let live = LiveConfig::new(
+            LiveInput::parse(input.to_str().unwrap()),
+            LiveFormat::Raw { width: 160, height: 120, fps: 10.0 },
+        )
+        .with_follow()
+        .with_idle_timeout(Some(Duration::from_millis(500)));
+
+        let mut events = Vec::new();
+        let count = VideoProcessor::new()
+            .unwrap()
+            .create_live_derivative_video(&live, output.to_str().unwrap(), 25, MotionEventDetector::new(0.001, 0.001, 2), |event| {
+                events.push(event.clone());
+                Ok(())
+            })
+            .unwrap();
+        recorder.join().unwrap();
+
+        assert_eq!(count, 15);
+        assert_eq!(Y4mReader::open(&output).unwrap().frames().count(), 15);
+        let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![MotionEventKind::Started, MotionEventKind::Ended]);
+        assert!((events[0].timestamp - 0.6).abs() < 1e-9);
+        assert!(events[1].duration.unwrap() > 0.0);
+        assert!(events[0].to_json().contains("\"kind\":\"started\""));
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        use crate::error::Error;
+        use crate::video::{FrameRange, Y4mReader};
+        use std::io::Cursor;
+
+        let reference = VideoFrame::new(image::RgbImage::new(4, 4), 0, 0.0);
+        let derivative = crate::SignedDerivative::<Rgb<u8>>::new(vec![0; 2 * 2 * 3], 2, 2, 1, 0.1);
+        let mut integrator = DerivativeIntegrator::with_reference(reference);
+        assert!(matches!(
+            integrator.integrate(&derivative),
+            Err(Error::DimensionMismatch { expected: (4, 4), actual: (2, 2) })
+        ));
+        assert!(matches!(DerivativeIntegrator::new().integrate(&derivative), Err(Error::MissingReference)));
+
+        assert!(matches!(FrameRange::default().every_nth(0).validate(), Err(Error::InvalidConfig(_))));
+        assert!(matches!("wide".parse::<crate::CompositeLayout>(), Err(Error::InvalidConfig(_))));
+        assert!(matches!(Y4mReader::new(Cursor::new(b"RIFF W4 H4\n".to_vec())), Err(Error::Decode(_))));
+        // Поврежденный заголовок: многобайтный символ и размеры, переполняющие u32
+        for header in ["YUV4MPEG2 W4 H4 \u{44f}4\n", "YUV4MPEG2 W65536 H65536\n", "YUV4MPEG2 W0 H4\n"] {
+            assert!(matches!(Y4mReader::new(Cursor::new(header.as_bytes().to_vec())), Err(Error::Decode(_))), "{}", header);
+        }
+        assert!(matches!(Y4mReader::open("/nonexistent/input.y4m"), Err(Error::File { .. })));
+
+        let failed = Error::FfmpegFailed { code: Some(1), stderr: "Invalid data".into() };
+        assert_eq!(failed.to_string(), "FFmpeg завершился с ошибкой (код 1): Invalid data");
+    }
+
+    #[test]
+    fn test_progress_reports() {
+        use crate::video::{Progress, ProgressStage, VideoProcessor, Y4mReader, VideoSource};
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames = FrameGenerator::new(160, 120, 30).generate_moving_object_frames(4);
+        for (i, frame) in frames.iter().enumerate() {
+            frame.data.save(dir.path().join(format!("frame_{:03}.png", i))).unwrap();
+        }
+        let output = dir.path().join("out.y4m");
+
+        let reports = Arc::new(Mutex::new(Vec::<Progress>::new()));
+        let collected = reports.clone();
+        VideoProcessor::new()
+            .unwrap()
+            .with_progress(move |progress: &Progress| collected.lock().unwrap().push(*progress))
+            .create_derivative_video(dir.path().to_str().unwrap(), output.to_str().unwrap(), 30, 25)
+            .unwrap();
+        assert_eq!(Y4mReader::open(&output).unwrap().frames().count(), 4);
+
+        let reports = reports.lock().unwrap();
+        let processing: Vec<_> = reports.iter().filter(|p| p.stage == ProgressStage::Processing).collect();
+        assert_eq!(processing.iter().map(|p| p.current).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(processing.iter().all(|p| p.total == Some(4)));
+        assert_eq!(reports.last().unwrap().stage, ProgressStage::Encoding);
+
+        let halfway = Progress { stage: ProgressStage::Processing, current: 25, total: Some(100), elapsed: Duration::from_secs(10) };
+        assert_eq!(halfway.fraction(), Some(0.25));
+        assert_eq!(halfway.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(Progress { total: None, ..halfway }.eta(), None);
+    }
+
+    #[test]
+    fn test_cancellation_stops_processing_and_removes_output() {
+        use crate::error::Error;
+        use crate::video::{CancellationToken, Progress, VideoProcessor};
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames = FrameGenerator::new(160, 120, 30).generate_moving_object_frames(6);
+        for (i, frame) in frames.iter().enumerate() {
+            frame.data.save(dir.path().join(format!("frame_{:03}.png", i))).unwrap();
+        }
+        let output = dir.path().join("out.y4m");
+
+        // Отмена из другого места программы - здесь из отчета о втором кадре
+        let token = CancellationToken::new();
+        let trigger = token.clone();
+        let result = VideoProcessor::new()
+            .unwrap()
+            .with_cancellation(token.clone())
+            .with_progress(move |progress: &Progress| {
+                if progress.current == 2 {
+                    trigger.cancel();
+                }
+            })
+            .create_derivative_video(dir.path().to_str().unwrap(), output.to_str().unwrap(), 30, 25);
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(token.is_cancelled());
+        assert!(!output.exists());
+
+        let extracted = VideoProcessor::new()
+            .unwrap()
+            .with_cancellation(token)
+            .extract_frames_from_video(dir.path().to_str().unwrap(), Some(30));
+        assert!(matches!(extracted, Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn test_frame_analysis_and_comparison() {
+        use crate::error::Error;
+        use crate::processors::{AnalysisSummary, ComparisonSummary, FrameAnalysis, FrameComparison, RegionDetector};
+        use crate::types::DifferenceBackend;
+        use crate::video::VideoProcessor;
+
+        let frames = FrameGenerator::new(160, 120, 30).generate_moving_object_frames(5);
+
+        let same = FrameComparison::between(&frames[0], &frames[0], 0).unwrap();
+        assert_eq!(same.mse, 0.0);
+        assert!(same.psnr.is_infinite());
+        let moved = FrameComparison::between(&frames[0], &frames[2], 0).unwrap();
+        assert!(moved.mse > 0.0 && moved.psnr.is_finite());
+        assert!(moved.differing_fraction > 0.0);
+
+        let mut summary = ComparisonSummary::default();
+        summary.add(&same);
+        assert!(summary.is_identical());
+        summary.add(&moved);
+        assert!(!summary.is_identical());
+        assert_eq!(summary.min_psnr, Some(moved.psnr));
+
+        let small = VideoFrame::new(ImageBuffer::new(80, 60), 0, 0.0);
+        assert!(matches!(
+            FrameComparison::between(&frames[0], &small, 0),
+            Err(Error::DimensionMismatch { .. })
+        ));
+
+        // Анализ производных последовательности изображений без FFmpeg
+        let dir = tempfile::tempdir().unwrap();
+        for (i, frame) in frames.iter().enumerate() {
+            frame.data.save(dir.path().join(format!("frame_{:03}.png", i))).unwrap();
+        }
+        let regions = RegionDetector::default();
+        let mut analysis = AnalysisSummary::new(0.001);
+        let scanned = VideoProcessor::new()
+            .unwrap()
+            .scan_derivatives(dir.path().to_str().unwrap(), Some(30), 25, |derivative| {
+                analysis.add(&FrameAnalysis::new(derivative, &regions));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(scanned, analysis.frames);
+        assert!(analysis.motion_frames > 0);
+        assert!(analysis.peak_changed_fraction >= analysis.mean_changed_fraction);
+
+        assert_eq!("slice".parse::<DifferenceBackend>().unwrap(), DifferenceBackend::Slice);
+        assert!("gpu".parse::<DifferenceBackend>().is_err());
+    }
+
+    #[test]
+    fn test_config_file_formats_and_validation() {
+        use crate::config::{ConfigFile, ConfigFormat};
+        use crate::error::Error;
+        use crate::types::{CropRect, GeometryStage};
+        use crate::video::{AudioMode, RateControl, VideoCodec};
+        use crate::CompositeLayout;
+
+        // Частичный файл: остальное по умолчанию
+        let config = ConfigFile::parse(
+            r#"
+            layout = "side-by-side"
+            audio = "aac"
+
+            [processing]
+            threshold = 12
+            time_normalization = { per-second = { scale = 0.04 } }
+            geometry = { crop = { x = 4, y = 8, width = 100, height = 60 }, stage = "after" }
+
+            [encoder]
+            codec = "ffv1"
+            "#,
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(config.layout, CompositeLayout::SideBySide);
+        assert_eq!(config.processing.threshold, 12);
+        assert_eq!(config.processing.fps, ProcessingConfig::default().fps);
+        assert_eq!(config.processing.geometry.crop, Some(CropRect::new(4, 8, 100, 60)));
+        assert_eq!(config.processing.geometry.stage, GeometryStage::AfterDifference);
+        // Без rate_control - значение по умолчанию для кодека
+        assert_eq!(config.encoder.codec, VideoCodec::Ffv1);
+        assert_eq!(config.encoder.rate_control, RateControl::Lossless);
+        assert_eq!(config.audio, AudioMode::aac());
+        let opus = ConfigFile::parse(r#"{"audio": {"codec": "libopus"}}"#, ConfigFormat::Json).unwrap();
+        assert_eq!(opus.audio, AudioMode::Reencode { codec: "libopus".into(), bitrate: None });
+        assert_eq!(ConfigFile::default().audio, AudioMode::None);
+
+        for format in [ConfigFormat::Toml, ConfigFormat::Json, ConfigFormat::Yaml] {
+            let text = config.to_text(format).unwrap();
+            assert_eq!(ConfigFile::parse(&text, format).unwrap(), config, "{}", format);
+        }
+
+        let invalid = [
+            ("[processing]\nthreshhold = 10", ConfigFormat::Toml),
+            ("[processing]\nthreshold = 300", ConfigFormat::Toml),
+            (r#"{"processing": {"fps": 0}}"#, ConfigFormat::Json),
+            ("encoder:\n  codec: ffv1\n  rate_control:\n    crf: 20", ConfigFormat::Yaml),
+            ("audio = \"mp3\"", ConfigFormat::Toml),
+        ];
+        for (text, format) in invalid {
+            assert!(matches!(ConfigFile::parse(text, format), Err(Error::InvalidConfig(_))), "{}", text);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("camera.yaml");
+        std::fs::write(&path, "processing:\n  backend: gpu\n").unwrap();
+        let message = ConfigFile::load(&path).unwrap_err().to_string();
+        assert!(message.contains("camera.yaml") && message.contains("gpu"), "{}", message);
+        assert_eq!(ConfigFormat::from_path("camera.yml").unwrap(), ConfigFormat::Yaml);
+        assert!(ConfigFormat::from_path("camera.ini").is_err());
+    }
+
+    #[test]
+    fn test_batch_mirrors_outputs_and_skips_done() {
+        use crate::error::Error;
+        use crate::video::{collect_inputs, read_manifest, BatchJob, BatchRunner, BatchStatus};
+        use std::fs;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = tempfile::tempdir().unwrap();
+        let clips = dir.path().join("clips");
+        fs::create_dir_all(clips.join("cam1")).unwrap();
+        for name in ["cam1/a.mp4", "cam1/b.mkv", "c.y4m", "notes.txt"] {
+            fs::write(clips.join(name), name).unwrap();
+        }
+        let out = dir.path().join("out");
+
+        let inputs = collect_inputs(&clips).unwrap();
+        assert_eq!(inputs.len(), 3);
+        let jobs = BatchJob::plan(&inputs, &out, "y4m").unwrap();
+        let outputs: Vec<_> = jobs.iter().map(|job| job.output.strip_prefix(&out).unwrap().to_path_buf()).collect();
+        assert_eq!(
+            outputs,
+            ["c_derivative.y4m", "cam1/a_derivative.y4m", "cam1/b_derivative.y4m"].map(std::path::PathBuf::from)
+        );
+
+        // Один вход падает: остальные обрабатываются, недописанный файл удаляется
+        let calls = AtomicUsize::new(0);
+        let process = |input: &std::path::Path, output: &std::path::Path| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            fs::write(output, b"derivative")?;
+            if input.ends_with("b.mkv") {
+                return Err(Error::decode("поврежденный файл"));
+            }
+            Ok(())
+        };
+        let finished = AtomicUsize::new(0);
+        let report = BatchRunner::new(2)
+            .run(&jobs, process, |_, _| {
+                finished.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        assert_eq!((report.total, report.done, report.failed), (3, 2, 1));
+        assert!(!report.is_success());
+        assert_eq!(finished.load(Ordering::SeqCst), 3);
+        assert_eq!(report.items[2].status, BatchStatus::Failed);
+        assert!(report.items[2].error.as_deref().unwrap().contains("поврежденный"));
+        assert!(out.join("cam1/a_derivative.y4m").exists());
+        assert!(!out.join("cam1/b_derivative.y4m").exists());
+        assert!(!jobs[2].partial_output().exists());
+
+        // Повторный запуск делает только то, что не готово
+        let report = BatchRunner::new(2).run(&jobs, process, |_, _| {}).unwrap();
+        assert_eq!((report.done, report.skipped, report.failed), (0, 2, 1));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        let report = BatchRunner::new(1).with_overwrite().run(&jobs[..1], process, |_, _| {}).unwrap();
+        assert_eq!(report.done, 1);
+
+        // Список: комментарии, шаблоны и повтор каталога записи в выходе
+        let manifest = dir.path().join("nightly.txt");
+        fs::write(&manifest, "# ночь\nclips/cam1/*.mp4\n\nclips/c.y4m\n").unwrap();
+        let listed = read_manifest(&manifest).unwrap();
+        let relative: Vec<_> = listed.iter().map(|input| input.relative.clone()).collect();
+        assert_eq!(relative, ["clips/cam1/a.mp4", "clips/c.y4m"].map(std::path::PathBuf::from));
+        fs::write(&manifest, "clips/missing.mp4\n").unwrap();
+        let message = read_manifest(&manifest).unwrap_err().to_string();
+        assert!(message.contains("nightly.txt:1"), "{}", message);
+
+        // Повторы одного файла отбрасываются, разные файлы с одним выходом - ошибка
+        let twice = [
+            collect_inputs(clips.join("cam1/a.mp4")).unwrap(),
+            collect_inputs(clips.join("cam1/*.mp4")).unwrap(),
+        ];
+        assert_eq!(BatchJob::plan(&twice.concat(), &out, "y4m").unwrap().len(), 1);
+        fs::write(clips.join("a.mp4"), "другой").unwrap();
+        let clash = [collect_inputs(clips.join("a.mp4")).unwrap(), collect_inputs(clips.join("cam1/a.mp4")).unwrap()];
+        assert!(BatchJob::plan(&clash.concat(), &out, "y4m").is_err());
+    }
+
+    #[test]
+    fn test_resolution_change_mid_stream() {
+        use crate::error::Error;
+        use crate::DifferenceBackend;
+
+        let large = VideoFrame::new(ImageBuffer::from_pixel(160, 120, Rgb([200, 10, 10])), 0, 0.0);
+        let small = VideoFrame::new(ImageBuffer::from_pixel(80, 60, Rgb([10, 10, 200])), 1, 0.04);
+        let wider = VideoFrame::new(ImageBuffer::from_pixel(200, 60, Rgb([10, 200, 10])), 2, 0.08);
+
+        for backend in [DifferenceBackend::PerPixel, DifferenceBackend::Slice, DifferenceBackend::Parallel] {
+            let mut processor = VideoDerivativeProcessor::new(ProcessingConfig { backend, ..ProcessingConfig::default() });
+            processor.process_frame(&large);
+            // Новый размер - новая опора: пустая производная вместо паники
+            for frame in [&small, &wider] {
+                let derivative = processor.process_frame(frame);
+                assert_eq!(derivative.dimensions(), frame.dimensions());
+                assert!(derivative.data.pixels().all(|pixel| pixel.0 == [0, 0, 0]));
+            }
+            assert!(matches!(
+                processor.compute_frame_difference(&large.data, &small.data),
+                Err(Error::DimensionMismatch { expected: (160, 120), actual: (80, 60) })
+            ));
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Числовые характеристики производных и сравнение видео кадр за кадром.
+
+use crate::error::{Error, Result};
+use crate::processors::events::changed_fraction;
+use crate::processors::regions::RegionDetector;
+use crate::types::VideoFrame;
+use serde::Serialize;
+
+/// Сводка по одной производной
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameAnalysis {
+    pub frame_number: usize,
+    pub timestamp: f64,
+    /// Доля изменившихся пикселей
+    pub changed_fraction: f32,
+    /// Средняя сила изменения по всем пикселям (0..255)
+    pub mean_intensity: f32,
+    /// Количество областей движения
+    pub regions: usize,
+}
+
+impl FrameAnalysis {
+    pub fn new(derivative: &VideoFrame, regions: &RegionDetector) -> Self {
+        let pixels = derivative.width() as usize * derivative.height() as usize;
+        let total: u64 = derivative
+            .data
+            .pixels()
+            .map(|pixel| pixel.0.into_iter().max().unwrap_or(0) as u64)
+            .sum();
+        Self {
+            frame_number: derivative.frame_number,
+            timestamp: derivative.timestamp,
+            changed_fraction: changed_fraction(&derivative.data),
+            mean_intensity: if pixels == 0 { 0.0 } else { total as f32 / pixels as f32 },
+            regions: regions.detect(&derivative.data).len(),
+        }
+    }
+
+    /// Одна строка JSON (формат JSON Lines)
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Итог анализа всего видео
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AnalysisSummary {
+    pub frames: usize,
+    /// Кадры, в которых изменилось больше `motion_fraction` пикселей
+    pub motion_frames: usize,
+    pub mean_changed_fraction: f32,
+    pub peak_changed_fraction: f32,
+    /// Кадр с наибольшей долей изменений
+    pub peak_frame: Option<usize>,
+    #[serde(skip)]
+    motion_fraction: f32,
+    #[serde(skip)]
+    changed_sum: f64,
+}
+
+impl AnalysisSummary {
+    pub fn new(motion_fraction: f32) -> Self {
+        Self { motion_fraction, ..Self::default() }
+    }
+
+    pub fn add(&mut self, frame: &FrameAnalysis) {
+        self.frames += 1;
+        if frame.changed_fraction > self.motion_fraction {
+            self.motion_frames += 1;
+        }
+        if self.peak_frame.is_none() || frame.changed_fraction > self.peak_changed_fraction {
+            self.peak_changed_fraction = frame.changed_fraction;
+            self.peak_frame = Some(frame.frame_number);
+        }
+        self.changed_sum += frame.changed_fraction as f64;
+        self.mean_changed_fraction = (self.changed_sum / self.frames as f64) as f32;
+    }
+}
+
+/// Отличие одного кадра от соответствующего кадра другого видео
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameComparison {
+    pub frame_number: usize,
+    pub timestamp: f64,
+    /// Среднеквадратичная ошибка по всем компонентам
+    pub mse: f64,
+    /// Пиковое отношение сигнал/шум в дБ; бесконечность для одинаковых кадров
+    pub psnr: f64,
+    /// Наибольшая разница компоненты
+    pub max_difference: u8,
+    /// Доля пикселей, различающихся больше чем на порог
+    pub differing_fraction: f32,
+}
+
+impl FrameComparison {
+    /// Сравнивает кадры одного размера; пиксель считается отличающимся,
+    /// если хотя бы одна компонента отличается больше чем на `tolerance`
+    pub fn between(first: &VideoFrame, second: &VideoFrame, tolerance: u8) -> Result<Self> {
+        if first.dimensions() != second.dimensions() {
+            return Err(Error::DimensionMismatch {
+                expected: first.dimensions(),
+                actual: second.dimensions(),
+            });
+        }
+
+        let mut squared = 0u64;
+        let mut max_difference = 0u8;
+        let mut differing = 0usize;
+        for (a, b) in first.data.pixels().zip(second.data.pixels()) {
+            let mut pixel_max = 0u8;
+            for (&x, &y) in a.0.iter().zip(&b.0) {
+                let difference = x.abs_diff(y);
+                squared += difference as u64 * difference as u64;
+                pixel_max = pixel_max.max(difference);
+            }
+            max_difference = max_difference.max(pixel_max);
+            if pixel_max > tolerance {
+                differing += 1;
+            }
+        }
+
+        let pixels = first.width() as usize * first.height() as usize;
+        let mse = if pixels == 0 { 0.0 } else { squared as f64 / (pixels * 3) as f64 };
+        let psnr = if mse == 0.0 { f64::INFINITY } else { 10.0 * (255.0 * 255.0 / mse).log10() };
+        Ok(Self {
+            frame_number: first.frame_number,
+            timestamp: first.timestamp,
+            mse,
+            psnr,
+            max_difference,
+            differing_fraction: if pixels == 0 { 0.0 } else { differing as f32 / pixels as f32 },
+        })
+    }
+
+    /// Одна строка JSON; бесконечный PSNR записывается как `null`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Итог сравнения двух видео
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ComparisonSummary {
+    pub frames: usize,
+    /// Кадры, где есть пиксели, отличающиеся больше порога
+    pub differing_frames: usize,
+    pub mean_mse: f64,
+    /// Наименьший PSNR (худший кадр); `None`, если все кадры совпадают
+    pub min_psnr: Option<f64>,
+    pub max_difference: u8,
+    #[serde(skip)]
+    mse_sum: f64,
+}
+
+impl ComparisonSummary {
+    pub fn add(&mut self, frame: &FrameComparison) {
+        self.frames += 1;
+        if frame.differing_fraction > 0.0 {
+            self.differing_frames += 1;
+        }
+        if frame.psnr.is_finite() {
+            self.min_psnr = Some(self.min_psnr.map_or(frame.psnr, |psnr| psnr.min(frame.psnr)));
+        }
+        self.max_difference = self.max_difference.max(frame.max_difference);
+        self.mse_sum += frame.mse;
+        self.mean_mse = self.mse_sum / self.frames as f64;
+    }
+
+    /// Видео совпадают с точностью до порога
+    pub fn is_identical(&self) -> bool {
+        self.differing_frames == 0
+    }
+}
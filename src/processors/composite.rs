@@ -0,0 +1,167 @@
+use crate::error::Error;
+use crate::types::VideoFrame;
+use image::{imageops, Rgb, RgbImage};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+/// Как совместить исходный кадр и производную в выходном видео
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompositeLayout {
+    /// Только производная
+    #[default]
+    #[serde(rename = "derivative")]
+    DerivativeOnly,
+    /// Исходник слева, производная справа
+    SideBySide,
+    /// Исходник сверху, производная снизу
+    Stacked,
+    /// 2x2: исходник, производная, бинарная маска движения, тепловая карта
+    Grid,
+    /// Исходник, на котором пиксели с движением смешаны с цветом `tint`
+    /// с непрозрачностью `opacity`
+    Overlay {
+        #[serde(default = "default_tint", with = "rgb_array")]
+        tint: Rgb<u8>,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+    },
+}
+
+fn default_tint() -> Rgb<u8> {
+    Rgb([255, 0, 0])
+}
+
+fn default_opacity() -> f32 {
+    0.6
+}
+
+/// `Rgb<u8>` в конфигурации записывается массивом `[r, g, b]`
+mod rgb_array {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Rgb<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        color.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgb<u8>, D::Error> {
+        <[u8; 3]>::deserialize(deserializer).map(Rgb)
+    }
+}
+
+impl CompositeLayout {
+    /// Красная подсветка движения с непрозрачностью 60%
+    pub fn overlay() -> Self {
+        CompositeLayout::Overlay { tint: default_tint(), opacity: default_opacity() }
+    }
+
+    /// Размер выходного кадра для входа `width`x`height`
+    pub fn output_dimensions(&self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            CompositeLayout::DerivativeOnly | CompositeLayout::Overlay { .. } => (width, height),
+            CompositeLayout::SideBySide => (width * 2, height),
+            CompositeLayout::Stacked => (width, height * 2),
+            CompositeLayout::Grid => (width * 2, height * 2),
+        }
+    }
+
+    /// Собирает выходной кадр; номер и метка времени берутся из производной
+    pub fn compose(&self, original: &VideoFrame, derivative: VideoFrame) -> VideoFrame {
+        let data = match self {
+            CompositeLayout::DerivativeOnly => return derivative,
+            CompositeLayout::SideBySide => tile(&[&original.data, &derivative.data], 2),
+            CompositeLayout::Stacked => tile(&[&original.data, &derivative.data], 1),
+            CompositeLayout::Grid => {
+                let mask = motion_mask(&derivative.data);
+                let heatmap = heatmap(&derivative.data);
+                tile(&[&original.data, &derivative.data, &mask, &heatmap], 2)
+            }
+            CompositeLayout::Overlay { tint, opacity } => {
+                tinted_overlay(&original.data, &derivative.data, *tint, *opacity)
+            }
+        };
+        VideoFrame::new(data, derivative.frame_number, derivative.timestamp)
+    }
+}
+
+impl FromStr for CompositeLayout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "derivative" | "none" => Ok(CompositeLayout::DerivativeOnly),
+            "side-by-side" | "sbs" | "hstack" => Ok(CompositeLayout::SideBySide),
+            "stacked" | "vstack" => Ok(CompositeLayout::Stacked),
+            "grid" => Ok(CompositeLayout::Grid),
+            "overlay" => Ok(CompositeLayout::overlay()),
+            other => Err(Error::config(format!(
+                "Неизвестная раскладка: {} (доступны derivative, side-by-side, stacked, grid, overlay)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Сила изменения пикселя производной: максимум по каналам
+fn intensity(pixel: &Rgb<u8>) -> u8 {
+    pixel.0.into_iter().max().unwrap_or(0)
+}
+
+/// Раскладывает кадры одинакового размера по сетке с `columns` столбцами
+fn tile(images: &[&RgbImage], columns: u32) -> RgbImage {
+    let (width, height) = images[0].dimensions();
+    let rows = (images.len() as u32).div_ceil(columns);
+    let mut canvas = RgbImage::new(width * columns, height * rows);
+    for (i, image) in images.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        imageops::replace(&mut canvas, *image, (column * width) as i64, (row * height) as i64);
+    }
+    canvas
+}
+
+/// Белые пиксели там, где производная ненулевая (порог уже применен процессором)
+pub fn motion_mask(derivative: &RgbImage) -> RgbImage {
+    RgbImage::from_fn(derivative.width(), derivative.height(), |x, y| {
+        if intensity(derivative.get_pixel(x, y)) > 0 {
+            Rgb([255, 255, 255])
+        } else {
+            Rgb([0, 0, 0])
+        }
+    })
+}
+
+/// Тепловая карта силы изменения: черный - синий - красный - желтый - белый
+pub fn heatmap(derivative: &RgbImage) -> RgbImage {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 255.0],
+        [255.0, 0.0, 0.0],
+        [255.0, 255.0, 0.0],
+        [255.0, 255.0, 255.0],
+    ];
+
+    let palette: Vec<Rgb<u8>> = (0..=255u32)
+        .map(|value| {
+            let position = value as f32 / 255.0 * (STOPS.len() - 1) as f32;
+            let index = (position as usize).min(STOPS.len() - 2);
+            let t = position - index as f32;
+            let (from, to) = (STOPS[index], STOPS[index + 1]);
+            Rgb(std::array::from_fn(|c| (from[c] + (to[c] - from[c]) * t).round() as u8))
+        })
+        .collect();
+
+    RgbImage::from_fn(derivative.width(), derivative.height(), |x, y| {
+        palette[intensity(derivative.get_pixel(x, y)) as usize]
+    })
+}
+
+fn tinted_overlay(original: &RgbImage, derivative: &RgbImage, tint: Rgb<u8>, opacity: f32) -> RgbImage {
+    let opacity = opacity.clamp(0.0, 1.0);
+    RgbImage::from_fn(original.width(), original.height(), |x, y| {
+        let alpha = if intensity(derivative.get_pixel(x, y)) > 0 { opacity } else { 0.0 };
+        let source = original.get_pixel(x, y);
+        Rgb(std::array::from_fn(|c| {
+            (source[c] as f32 * (1.0 - alpha) + tint[c] as f32 * alpha).round() as u8
+        }))
+    })
+}
@@ -0,0 +1,147 @@
+use crate::types::VideoFrame;
+use image::RgbImage;
+use serde::Serialize;
+
+/// Тип события движения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionEventKind {
+    Started,
+    Ended,
+}
+
+/// Начало или конец движения в кадре
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MotionEvent {
+    pub kind: MotionEventKind,
+    pub frame_number: usize,
+    pub timestamp: f64,
+    /// Доля изменившихся пикселей в кадре события
+    pub changed_fraction: f32,
+    /// Для `Ended`: наибольшая доля за все событие
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_fraction: Option<f32>,
+    /// Для `Ended`: длительность события в секундах
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+}
+
+impl MotionEvent {
+    /// Одна строка JSON (формат JSON Lines)
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Доля ненулевых пикселей производной (порог уже применен процессором)
+pub fn changed_fraction(derivative: &RgbImage) -> f32 {
+    let total = derivative.width() as usize * derivative.height() as usize;
+    if total == 0 {
+        return 0.0;
+    }
+    let changed = derivative.pixels().filter(|pixel| pixel.0.iter().any(|&c| c > 0)).count();
+    changed as f32 / total as f32
+}
+
+/// Находит события движения по потоку производных. Событие начинается,
+/// когда доля изменившихся пикселей превышает `start_fraction`, и
+/// заканчивается после `min_quiet_frames` кадров подряд ниже `end_fraction`
+/// (гистерезис, чтобы шум на границе порога не дробил событие).
+#[derive(Debug, Clone)]
+pub struct MotionEventDetector {
+    pub start_fraction: f32,
+    pub end_fraction: f32,
+    pub min_quiet_frames: usize,
+    active: Option<ActiveEvent>,
+    quiet: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveEvent {
+    start: f64,
+    peak: f32,
+    /// Последний кадр с движением - им событие и заканчивается
+    last_frame: usize,
+    last_timestamp: f64,
+    last_fraction: f32,
+}
+
+impl Default for MotionEventDetector {
+    fn default() -> Self {
+        Self::new(0.01, 0.005, 5)
+    }
+}
+
+impl MotionEventDetector {
+    pub fn new(start_fraction: f32, end_fraction: f32, min_quiet_frames: usize) -> Self {
+        Self {
+            start_fraction,
+            end_fraction: end_fraction.min(start_fraction),
+            min_quiet_frames: min_quiet_frames.max(1),
+            active: None,
+            quiet: 0,
+        }
+    }
+
+    /// Идет ли сейчас событие
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Учитывает очередную производную и возвращает событие, если оно
+    /// началось или закончилось на этом кадре
+    pub fn update(&mut self, derivative: &VideoFrame) -> Option<MotionEvent> {
+        let fraction = changed_fraction(&derivative.data);
+        match &mut self.active {
+            None if fraction > self.start_fraction => {
+                self.quiet = 0;
+                self.active = Some(ActiveEvent {
+                    start: derivative.timestamp,
+                    peak: fraction,
+                    last_frame: derivative.frame_number,
+                    last_timestamp: derivative.timestamp,
+                    last_fraction: fraction,
+                });
+                Some(MotionEvent {
+                    kind: MotionEventKind::Started,
+                    frame_number: derivative.frame_number,
+                    timestamp: derivative.timestamp,
+                    changed_fraction: fraction,
+                    peak_fraction: None,
+                    duration: None,
+                })
+            }
+            None => None,
+            Some(active) if fraction >= self.end_fraction => {
+                self.quiet = 0;
+                active.peak = active.peak.max(fraction);
+                active.last_frame = derivative.frame_number;
+                active.last_timestamp = derivative.timestamp;
+                active.last_fraction = fraction;
+                None
+            }
+            Some(_) => {
+                self.quiet += 1;
+                if self.quiet >= self.min_quiet_frames {
+                    self.finish()
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Закрывает незавершенное событие (например, в конце потока)
+    pub fn finish(&mut self) -> Option<MotionEvent> {
+        let active = self.active.take()?;
+        self.quiet = 0;
+        Some(MotionEvent {
+            kind: MotionEventKind::Ended,
+            frame_number: active.last_frame,
+            timestamp: active.last_timestamp,
+            changed_fraction: active.last_fraction,
+            peak_fraction: Some(active.peak),
+            duration: Some(active.last_timestamp - active.start),
+        })
+    }
+}
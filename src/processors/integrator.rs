@@ -0,0 +1,96 @@
+use crate::error::{Error, Result};
+use crate::types::{VideoFrame, SignedDerivative, DerivativePacket, FramePixel};
+use image::Rgb;
+
+/// Обратная операция к `VideoDerivativeProcessor::process_frame_signed`:
+/// накапливает знаковые производные поверх опорного кадра и восстанавливает
+/// исходную последовательность. Каждый опорный кадр в потоке сбрасывает
+/// накопленную ошибку.
+pub struct DerivativeIntegrator<P: FramePixel = Rgb<u8>> {
+    current: Option<VideoFrame<P>>,
+    frames_since_keyframe: usize,
+}
+
+impl<P: FramePixel> DerivativeIntegrator<P> {
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            frames_since_keyframe: 0,
+        }
+    }
+
+    /// Создает интегратор с заданным опорным кадром
+    pub fn with_reference(reference: VideoFrame<P>) -> Self {
+        let mut integrator = Self::new();
+        integrator.reanchor(reference);
+        integrator
+    }
+
+    /// Заменяет текущее состояние опорным кадром
+    pub fn reanchor(&mut self, reference: VideoFrame<P>) {
+        self.current = Some(reference);
+        self.frames_since_keyframe = 0;
+    }
+
+    /// Прибавляет производную к текущему кадру и возвращает восстановленный кадр
+    pub fn integrate(&mut self, derivative: &SignedDerivative<P>) -> Result<VideoFrame<P>> {
+        let current = self
+            .current
+            .as_ref()
+            .ok_or(Error::MissingReference)?;
+
+        if current.dimensions() != derivative.dimensions() {
+            return Err(Error::DimensionMismatch {
+                expected: current.dimensions(),
+                actual: derivative.dimensions(),
+            });
+        }
+
+        let frame = VideoFrame::from_buffer(
+            derivative.apply_to(&current.data),
+            derivative.frame_number,
+            derivative.timestamp,
+        );
+
+        self.current = Some(frame.clone());
+        self.frames_since_keyframe += 1;
+        Ok(frame)
+    }
+
+    /// Обрабатывает очередной элемент потока
+    pub fn push(&mut self, packet: &DerivativePacket<P>) -> Result<VideoFrame<P>> {
+        match packet {
+            DerivativePacket::Keyframe(frame) => {
+                self.reanchor(frame.clone());
+                Ok(frame.clone())
+            }
+            DerivativePacket::Delta(derivative) => self.integrate(derivative),
+        }
+    }
+
+    pub fn current(&self) -> Option<&VideoFrame<P>> {
+        self.current.as_ref()
+    }
+
+    /// Число производных, прибавленных после последнего опорного кадра
+    pub fn frames_since_keyframe(&self) -> usize {
+        self.frames_since_keyframe
+    }
+
+    pub fn reset(&mut self) {
+        self.current = None;
+        self.frames_since_keyframe = 0;
+    }
+
+    /// Восстанавливает всю последовательность кадров из потока производных
+    pub fn reconstruct(packets: &[DerivativePacket<P>]) -> Result<Vec<VideoFrame<P>>> {
+        let mut integrator = Self::new();
+        packets.iter().map(|packet| integrator.push(packet)).collect()
+    }
+}
+
+impl<P: FramePixel> Default for DerivativeIntegrator<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,2 +1,14 @@
 pub mod video_derivative;
-pub use video_derivative::VideoDerivativeProcessor;
\ No newline at end of file
+pub mod integrator;
+pub mod composite;
+pub mod regions;
+pub mod tracker;
+pub mod events;
+pub mod analysis;
+pub use video_derivative::VideoDerivativeProcessor;
+pub use integrator::DerivativeIntegrator;
+pub use composite::CompositeLayout;
+pub use regions::{MotionRegion, RegionDetector};
+pub use tracker::{MotionTracker, Track, TrailPoint};
+pub use events::{MotionEvent, MotionEventDetector, MotionEventKind};
+pub use analysis::{AnalysisSummary, ComparisonSummary, FrameAnalysis, FrameComparison};
@@ -0,0 +1,187 @@
+use image::RgbImage;
+
+/// Связная область движения на кадре производной
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Количество изменившихся пикселей
+    pub area: u32,
+    /// Центр масс изменившихся пикселей
+    pub centroid: (f32, f32),
+    /// Средняя сила изменения (0..255)
+    pub mean_intensity: f32,
+}
+
+impl MotionRegion {
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    /// Расстояние между рамками по ближайшим краям (0 для пересекающихся)
+    fn gap(&self, other: &MotionRegion) -> u32 {
+        let dx = other.x.saturating_sub(self.right()).max(self.x.saturating_sub(other.right()));
+        let dy = other.y.saturating_sub(self.bottom()).max(self.y.saturating_sub(other.bottom()));
+        dx.max(dy)
+    }
+
+    fn merge(&self, other: &MotionRegion) -> MotionRegion {
+        let area = self.area + other.area;
+        let weight = |a: f32, b: f32| (a * self.area as f32 + b * other.area as f32) / area as f32;
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        MotionRegion {
+            x,
+            y,
+            width: self.right().max(other.right()) - x,
+            height: self.bottom().max(other.bottom()) - y,
+            area,
+            centroid: (
+                weight(self.centroid.0, other.centroid.0),
+                weight(self.centroid.1, other.centroid.1),
+            ),
+            mean_intensity: weight(self.mean_intensity, other.mean_intensity),
+        }
+    }
+}
+
+/// Поиск областей движения: связные компоненты ненулевых пикселей
+/// производной (8-связность), близкие рамки объединяются, мелкие - отбрасываются
+#[derive(Debug, Clone)]
+pub struct RegionDetector {
+    /// Минимальная площадь области в пикселях
+    pub min_area: u32,
+    /// Рамки, между которыми не больше стольких пикселей, объединяются
+    pub merge_distance: u32,
+}
+
+impl Default for RegionDetector {
+    fn default() -> Self {
+        Self {
+            min_area: 20,
+            merge_distance: 8,
+        }
+    }
+}
+
+impl RegionDetector {
+    pub fn detect(&self, derivative: &RgbImage) -> Vec<MotionRegion> {
+        let (width, height) = derivative.dimensions();
+        let intensity = |x: u32, y: u32| derivative.get_pixel(x, y).0.into_iter().max().unwrap_or(0);
+
+        let mut visited = vec![false; (width * height) as usize];
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+
+        for start_y in 0..height {
+            for start_x in 0..width {
+                let start = (start_y * width + start_x) as usize;
+                if visited[start] || intensity(start_x, start_y) == 0 {
+                    continue;
+                }
+                visited[start] = true;
+                stack.push((start_x, start_y));
+
+                let (mut min_x, mut min_y, mut max_x, mut max_y) = (start_x, start_y, start_x, start_y);
+                let (mut area, mut sum_x, mut sum_y, mut sum_intensity) = (0u32, 0f64, 0f64, 0f64);
+
+                while let Some((x, y)) = stack.pop() {
+                    area += 1;
+                    sum_x += x as f64;
+                    sum_y += y as f64;
+                    sum_intensity += intensity(x, y) as f64;
+                    min_x = min_x.min(x);
+                    min_y = min_y.min(y);
+                    max_x = max_x.max(x);
+                    max_y = max_y.max(y);
+
+                    for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                        for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                            let index = (ny * width + nx) as usize;
+                            if !visited[index] && intensity(nx, ny) > 0 {
+                                visited[index] = true;
+                                stack.push((nx, ny));
+                            }
+                        }
+                    }
+                }
+
+                regions.push(MotionRegion {
+                    x: min_x,
+                    y: min_y,
+                    width: max_x - min_x + 1,
+                    height: max_y - min_y + 1,
+                    area,
+                    centroid: ((sum_x / area as f64) as f32, (sum_y / area as f64) as f32),
+                    mean_intensity: (sum_intensity / area as f64) as f32,
+                });
+            }
+        }
+
+        let mut regions = merge_close(regions, self.merge_distance);
+        regions.retain(|region| region.area >= self.min_area);
+        // Крупные области первыми
+        regions.sort_by_key(|region| std::cmp::Reverse(region.area));
+        regions
+    }
+}
+
+/// Объединяет рамки, пока находятся пары ближе `distance`. Объединенная
+/// рамка шире исходных и может дотянуться до новых соседей, поэтому проходы
+/// повторяются, пока число рамок уменьшается
+fn merge_close(mut regions: Vec<MotionRegion>, distance: u32) -> Vec<MotionRegion> {
+    loop {
+        let count = regions.len();
+        regions = merge_pass(regions, distance);
+        if regions.len() == count {
+            return regions;
+        }
+    }
+}
+
+/// Один проход: близкие пары собираются в группы через систему непересекающихся
+/// множеств. Рамки перебираются по возрастанию `x`, и для каждой проверяются
+/// только соседи, начинающиеся не дальше `distance` от ее правого края
+fn merge_pass(regions: Vec<MotionRegion>, distance: u32) -> Vec<MotionRegion> {
+    let mut order: Vec<usize> = (0..regions.len()).collect();
+    order.sort_by_key(|&i| regions[i].x);
+
+    let mut parent: Vec<usize> = (0..regions.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for (position, &i) in order.iter().enumerate() {
+        let reach = regions[i].right().saturating_add(distance);
+        for &j in &order[position + 1..] {
+            if regions[j].x > reach {
+                break;
+            }
+            if regions[i].gap(&regions[j]) <= distance {
+                let (root_i, root_j) = (find(&mut parent, i), find(&mut parent, j));
+                // Корень - меньший индекс, чтобы группа заняла место первой рамки
+                parent[root_i.max(root_j)] = root_i.min(root_j);
+            }
+        }
+    }
+
+    let mut groups: Vec<Option<MotionRegion>> = vec![None; regions.len()];
+    for (i, region) in regions.iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups[root] = Some(match groups[root] {
+            Some(group) => group.merge(region),
+            None => *region,
+        });
+    }
+    groups.into_iter().flatten().collect()
+}
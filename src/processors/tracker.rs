@@ -0,0 +1,145 @@
+use crate::processors::regions::MotionRegion;
+
+/// Точка траектории трека
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrailPoint {
+    pub frame_number: usize,
+    pub timestamp: f64,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Объект, прослеженный через несколько кадров
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub id: u64,
+    /// Последняя сопоставленная область
+    pub region: MotionRegion,
+    pub trail: Vec<TrailPoint>,
+    /// Сколько кадров подряд трек не находил область
+    pub missed: usize,
+}
+
+impl Track {
+    pub fn first_seen(&self) -> Option<&TrailPoint> {
+        self.trail.first()
+    }
+
+    pub fn last_seen(&self) -> Option<&TrailPoint> {
+        self.trail.last()
+    }
+
+    /// Виден ли трек на последнем обработанном кадре
+    pub fn is_visible(&self) -> bool {
+        self.missed == 0
+    }
+}
+
+/// Простой трекер: области соседних кадров связываются жадно по
+/// ближайшим центрам масс. Трек удаляется, если пропал дольше `max_missed` кадров.
+#[derive(Debug, Clone)]
+pub struct MotionTracker {
+    /// Максимальный сдвиг центра между кадрами в пикселях
+    pub max_distance: f32,
+    pub max_missed: usize,
+    /// Сколько последних точек траектории хранить (0 - все)
+    pub trail_capacity: usize,
+    tracks: Vec<Track>,
+    next_id: u64,
+}
+
+impl Default for MotionTracker {
+    fn default() -> Self {
+        Self::new(50.0, 5)
+    }
+}
+
+impl MotionTracker {
+    pub fn new(max_distance: f32, max_missed: usize) -> Self {
+        Self {
+            max_distance,
+            max_missed,
+            trail_capacity: 0,
+            tracks: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn with_trail_capacity(mut self, capacity: usize) -> Self {
+        self.trail_capacity = capacity;
+        self
+    }
+
+    /// Активные треки (включая временно пропавшие)
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Сопоставляет области нового кадра с треками. Возвращает треки,
+    /// удаленные на этом шаге (объект ушел из кадра).
+    pub fn update(&mut self, frame_number: usize, timestamp: f64, regions: &[MotionRegion]) -> Vec<Track> {
+        let distance = |track: &Track, region: &MotionRegion| {
+            let (dx, dy) = (track.region.centroid.0 - region.centroid.0, track.region.centroid.1 - region.centroid.1);
+            (dx * dx + dy * dy).sqrt()
+        };
+
+        let mut pairs: Vec<(f32, usize, usize)> = self
+            .tracks
+            .iter()
+            .enumerate()
+            .flat_map(|(t, track)| regions.iter().enumerate().map(move |(r, region)| (distance(track, region), t, r)))
+            .filter(|(d, _, _)| *d <= self.max_distance)
+            .collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut track_matched = vec![false; self.tracks.len()];
+        let mut region_matched = vec![false; regions.len()];
+        for (_, t, r) in pairs {
+            if track_matched[t] || region_matched[r] {
+                continue;
+            }
+            track_matched[t] = true;
+            region_matched[r] = true;
+            let track = &mut self.tracks[t];
+            track.region = regions[r];
+            track.missed = 0;
+            push_point(track, frame_number, timestamp, self.trail_capacity);
+        }
+
+        for (track, matched) in self.tracks.iter_mut().zip(&track_matched) {
+            if !matched {
+                track.missed += 1;
+            }
+        }
+
+        for (region, _) in regions.iter().zip(&region_matched).filter(|(_, matched)| !**matched) {
+            let mut track = Track {
+                id: self.next_id,
+                region: *region,
+                trail: Vec::new(),
+                missed: 0,
+            };
+            push_point(&mut track, frame_number, timestamp, self.trail_capacity);
+            self.tracks.push(track);
+            self.next_id += 1;
+        }
+
+        let max_missed = self.max_missed;
+        let (lost, active): (Vec<Track>, Vec<Track>) =
+            self.tracks.drain(..).partition(|track| track.missed > max_missed);
+        self.tracks = active;
+        lost
+    }
+}
+
+fn push_point(track: &mut Track, frame_number: usize, timestamp: f64, capacity: usize) {
+    track.trail.push(TrailPoint {
+        frame_number,
+        timestamp,
+        x: track.region.centroid.0,
+        y: track.region.centroid.1,
+    });
+    if capacity > 0 && track.trail.len() > capacity {
+        track.trail.remove(0);
+    }
+}
@@ -1,36 +1,90 @@
-use crate::types::{VideoFrame, ProcessingConfig};
-use image::{Frame, GenericImageView, ImageBuffer, Rgb, RgbImage};
-use std::path::Path;
+use crate::error::{Error, Result};
+use crate::types::{VideoFrame, ProcessingConfig, DifferenceBackend, TimeNormalization, SignedDerivative, DerivativePacket, FrameBuffer, FramePixel, GeometryStage, Sample};
+use image::{Primitive, Rgb};
+use rayon::prelude::*;
 
-pub struct VideoDerivativeProcessor {
-    previous_frame: Option<VideoFrame>,
+pub struct VideoDerivativeProcessor<P: FramePixel = Rgb<u8>> {
+    previous_frame: Option<VideoFrame<P>>,
     config: ProcessingConfig,
     frame_counter: usize,
 }
 
 impl VideoDerivativeProcessor {
     pub fn new(config: ProcessingConfig) -> Self {
+        Self::for_format(config)
+    }
+}
+
+impl<P: FramePixel> VideoDerivativeProcessor<P> {
+    /// Создает процессор для произвольного формата пикселей:
+    /// `VideoDerivativeProcessor::<Luma<u16>>::for_format(config)`
+    pub fn for_format(config: ProcessingConfig) -> Self {
         Self {
             previous_frame: None,
             config,
             frame_counter: 0,
         }
     }
-    pub fn process_frame(&mut self, frame: &VideoFrame) -> VideoFrame {
-        let derivative_data = match &self.previous_frame {
-            Some(prev_frame) => {
-                if self.config.noise_reduction {
-                    self.compute_thresholded_difference(&prev_frame.data, &frame.data)
+    /// Производная очередного кадра в выходной геометрии (`config.geometry`)
+    pub fn process_frame(&mut self, frame: &VideoFrame<P>) -> VideoFrame<P> {
+        let geometry = self.config.geometry;
+        if geometry.is_identity() {
+            return self.derive(frame);
+        }
+        match geometry.stage {
+            GeometryStage::BeforeDifference => self.derive(&self.transform(frame)),
+            GeometryStage::AfterDifference => {
+                let derivative = self.derive(frame);
+                self.transform(&derivative)
+            }
+        }
+    }
 
-                }else{
-                    self.compute_thresholded_difference(&prev_frame.data, &frame.data)
-                }
+    /// Как `process_frame`, но возвращает и исходный кадр в выходной
+    /// геометрии - для раскладок, где он стоит рядом с производной.
+    /// При обработке до разницы кадр масштабируется только один раз.
+    pub fn process_frame_with_original(&mut self, frame: VideoFrame<P>) -> (VideoFrame<P>, VideoFrame<P>) {
+        let geometry = self.config.geometry;
+        if geometry.is_identity() {
+            let derivative = self.derive(&frame);
+            return (frame, derivative);
+        }
+        match geometry.stage {
+            GeometryStage::BeforeDifference => {
+                let original = self.transform(&frame);
+                let derivative = self.derive(&original);
+                (original, derivative)
+            }
+            GeometryStage::AfterDifference => {
+                let derivative = self.derive(&frame);
+                (self.transform(&frame), self.transform(&derivative))
+            }
+        }
+    }
+
+    /// Обрезает и масштабирует кадр по `config.geometry`
+    pub fn transform(&self, frame: &VideoFrame<P>) -> VideoFrame<P> {
+        self.config.geometry.apply(frame, self.config.output_size())
+    }
+
+    fn derive(&mut self, frame: &VideoFrame<P>) -> VideoFrame<P> {
+        let derivative_data = match &self.previous_frame {
+            // Смена разрешения посреди потока (Y4M, живой вход) - как начало
+            // нового потока: разницы с кадром другого размера нет
+            Some(prev_frame) if prev_frame.dimensions() == frame.dimensions() => {
+                let threshold = self
+                    .config
+                    .noise_reduction
+                    .then(|| P::Subpixel::threshold_from_u8(self.config.threshold));
+                let gain = self.time_gain(prev_frame.timestamp, frame.timestamp);
+                self.difference(&prev_frame.data, &frame.data, threshold, gain)
+                    .unwrap_or_else(|_| Self::blank_frame(frame.width(), frame.height()))
             }
-            None =>{
-                ImageBuffer::new(frame.width(), frame.height())
+            _ =>{
+                Self::blank_frame(frame.width(), frame.height())
             }
         };
-        let derivative_frame = VideoFrame::new(
+        let derivative_frame = VideoFrame::from_buffer(
             derivative_data, self.frame_counter, frame.timestamp
         );
 
@@ -38,45 +92,108 @@ impl VideoDerivativeProcessor {
         self.frame_counter+=1;
         derivative_frame
     }
-    pub fn compute_frame_difference(&self, frame1: &RgbImage, frame2: &RgbImage) -> RgbImage {
-        let (width, height) = (frame1.width(), frame2.height());
-        let mut derivative = RgbImage::new(width, height);
+    /// Обрабатывает кадр в знаковом режиме: первый кадр и каждый `keyframe_interval`-й
+    /// выдаются как опорные, остальные - как разница к предыдущему кадру.
+    /// При включенном `noise_reduction` малые разницы обнуляются, поэтому
+    /// восстановление становится приближенным, а опорные кадры ограничивают дрейф.
+    /// Геометрия применяется к входному кадру независимо от `geometry.stage`,
+    /// чтобы опорные кадры и разницы имели один размер.
+    pub fn process_frame_signed(&mut self, frame: &VideoFrame<P>) -> DerivativePacket<P> {
+        if !self.config.geometry.is_identity() {
+            let transformed = self.transform(frame);
+            return self.derive_signed(&transformed);
+        }
+        self.derive_signed(frame)
+    }
 
-        for (x, y, pixel) in derivative.enumerate_pixels_mut() {
-            if x < width && y < height {
-                let pixel1 = frame1.get_pixel(x, y);
-                let pixel2 = frame2.get_pixel(x, y);
+    fn derive_signed(&mut self, frame: &VideoFrame<P>) -> DerivativePacket<P> {
+        let interval = self.config.keyframe_interval;
+        let is_keyframe = self.frame_counter == 0 || (interval > 0 && self.frame_counter.is_multiple_of(interval));
 
-                let diff_r = (pixel1[0] as i16 - pixel2[0] as i16).abs() as u8;
-                let diff_g = (pixel1[1] as i16 - pixel2[1] as i16).abs() as u8;
-                let diff_b = (pixel1[2] as i16 - pixel2[2] as i16).abs() as u8;
+        let packet = match &self.previous_frame {
+            Some(prev_frame) if !is_keyframe && prev_frame.dimensions() == frame.dimensions() => {
+                let mut derivative = SignedDerivative::between(
+                    &prev_frame.data, &frame.data, self.frame_counter, frame.timestamp
+                );
+                if self.config.noise_reduction {
+                    derivative.apply_threshold(self.config.threshold);
+                }
+                DerivativePacket::Delta(derivative)
+            }
+            _ => DerivativePacket::Keyframe(
+                VideoFrame::from_buffer(frame.data.clone(), self.frame_counter, frame.timestamp)
+            ),
+        };
 
-                *pixel = Rgb([diff_r, diff_g, diff_b]);
+        self.previous_frame = Some(frame.clone());
+        self.frame_counter+=1;
+        packet
+    }
+    /// Модуль разницы двух кадров одного размера
+    pub fn compute_frame_difference(&self, frame1: &FrameBuffer<P>, frame2: &FrameBuffer<P>) -> Result<FrameBuffer<P>> {
+        self.difference(frame1, frame2, None, None)
+    }
+    /// Множитель разницы при нормировке по времени. Если метки времени не
+    /// растут (поврежденный поток), интервал берется из `config.fps`.
+    fn time_gain(&self, previous_timestamp: f64, timestamp: f64) -> Option<f32> {
+        match self.config.time_normalization {
+            TimeNormalization::None => None,
+            TimeNormalization::PerSecond { scale } => {
+                let interval = timestamp - previous_timestamp;
+                let interval = if interval > 0.0 {
+                    interval
+                } else {
+                    1.0 / self.config.fps.max(1) as f64
+                };
+                Some((scale as f64 / interval) as f32)
             }
         }
-
-        derivative
     }
-    fn compute_thresholded_difference(&self, frame1: &RgbImage, frame2: &RgbImage) -> RgbImage {
-        let (width, height) = (frame1.width(), frame2.height());
-        let mut derivative = RgbImage::new(width, height);
-
-
-        for (x, y, pixel) in derivative.enumerate_pixels_mut() {
-            let pixel1 = frame1.get_pixel(x, y);
-            let pixel2 = frame2.get_pixel(x, y);
-
-            let diff_r = (pixel1[0] as i16 - pixel2[0] as i16).abs() as u8;
-            let diff_g = (pixel1[1] as i16 - pixel2[1] as i16).abs() as u8;
-            let diff_b = (pixel1[2] as i16 - pixel2[2] as i16).abs() as u8;
-
-            let result_r = if diff_r>self.config.threshold{diff_r}else{0};
-            let result_g = if diff_r>self.config.threshold{diff_g}else{0};
-            let result_b = if diff_r>self.config.threshold{diff_b}else{0};
+    /// Модуль разницы по цветовым каналам, умноженный на `gain` (если задан).
+    /// С порогом пиксель обнуляется целиком, если ни один цветовой канал не
+    /// изменился сильнее порога. Альфа-канал в результате всегда непрозрачный.
+    fn difference(&self, frame1: &FrameBuffer<P>, frame2: &FrameBuffer<P>, threshold: Option<P::Subpixel>, gain: Option<f32>) -> Result<FrameBuffer<P>> {
+        if frame1.dimensions() != frame2.dimensions() {
+            return Err(Error::DimensionMismatch { expected: frame1.dimensions(), actual: frame2.dimensions() });
+        }
+        let (width, height) = frame1.dimensions();
+        let mut derivative = FrameBuffer::<P>::new(width, height);
+        let channels = P::CHANNEL_COUNT as usize;
 
-            *pixel = Rgb([result_r, result_g, result_b]);
+        match self.config.backend {
+            DifferenceBackend::PerPixel => {
+                for (x, y, pixel) in derivative.enumerate_pixels_mut() {
+                    let pixel1 = frame1.get_pixel(x, y).channels();
+                    let pixel2 = frame2.get_pixel(x, y).channels();
+                    difference_pixel::<P>(pixel1, pixel2, pixel.channels_mut(), threshold, gain);
+                }
+            }
+            DifferenceBackend::Slice => {
+                difference_slice::<P>(frame1.as_raw(), frame2.as_raw(), &mut derivative, threshold, gain);
+            }
+            DifferenceBackend::Parallel => {
+                let row_len = width as usize * channels;
+                if row_len > 0 {
+                    derivative
+                        .par_chunks_mut(row_len)
+                        .zip(frame1.as_raw().par_chunks(row_len))
+                        .zip(frame2.as_raw().par_chunks(row_len))
+                        .for_each(|((out, row1), row2)| difference_slice::<P>(row1, row2, out, threshold, gain));
+                }
+            }
         }
-        derivative
+        Ok(derivative)
+    }
+    /// Пустая производная: нулевые цветовые каналы и непрозрачный альфа-канал
+    fn blank_frame(width: u32, height: u32) -> FrameBuffer<P> {
+        let mut frame = FrameBuffer::<P>::new(width, height);
+        if P::HAS_ALPHA {
+            let alpha = P::CHANNEL_COUNT as usize - 1;
+            for pixel in frame.pixels_mut() {
+                pixel.channels_mut()[alpha] = P::Subpixel::DEFAULT_MAX_VALUE;
+            }
+        }
+        frame
     }
     pub fn reset(&mut self){
         self.previous_frame = None;
@@ -88,4 +205,41 @@ impl VideoDerivativeProcessor {
     pub fn update_config(&mut self, config:ProcessingConfig){
         self.config = config;
     }
-}
\ No newline at end of file
+}
+
+/// Разница одного пикселя, общая для всех реализаций
+#[inline(always)]
+fn difference_pixel<P: FramePixel>(pixel1: &[P::Subpixel], pixel2: &[P::Subpixel], out: &mut [P::Subpixel], threshold: Option<P::Subpixel>, gain: Option<f32>) {
+    let color_channels = P::CHANNEL_COUNT as usize - P::HAS_ALPHA as usize;
+    let zero = P::Subpixel::DEFAULT_MIN_VALUE;
+
+    let mut max_diff = zero;
+    for c in 0..color_channels {
+        let mut diff = if pixel1[c] > pixel2[c] { pixel1[c] - pixel2[c] } else { pixel2[c] - pixel1[c] };
+        if let Some(gain) = gain {
+            diff = P::Subpixel::from_unit(diff.to_unit() * gain);
+        }
+        if diff > max_diff {
+            max_diff = diff;
+        }
+        out[c] = diff;
+    }
+    if threshold.is_some_and(|t| max_diff <= t) {
+        out[..color_channels].fill(zero);
+    }
+    if P::HAS_ALPHA {
+        out[color_channels] = P::Subpixel::DEFAULT_MAX_VALUE;
+    }
+}
+
+/// Разница непрерывных срезов компонент (целые строки или весь кадр)
+fn difference_slice<P: FramePixel>(data1: &[P::Subpixel], data2: &[P::Subpixel], out: &mut [P::Subpixel], threshold: Option<P::Subpixel>, gain: Option<f32>) {
+    let channels = P::CHANNEL_COUNT as usize;
+    for ((out, pixel1), pixel2) in out
+        .chunks_exact_mut(channels)
+        .zip(data1.chunks_exact(channels))
+        .zip(data2.chunks_exact(channels))
+    {
+        difference_pixel::<P>(pixel1, pixel2, out, threshold, gain);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::processors::{MotionRegion, MotionTracker, RegionDetector, Track};
+use crate::render::draw::{draw_label, draw_line, draw_rect};
+use crate::render::font::GLYPH_HEIGHT;
+use crate::types::VideoFrame;
+use image::{Rgb, RgbImage};
+
+/// Цвета треков: соседние номера хорошо различаются
+const TRACK_COLORS: [Rgb<u8>; 8] = [
+    Rgb([255, 64, 64]),
+    Rgb([64, 200, 255]),
+    Rgb([255, 220, 0]),
+    Rgb([120, 255, 80]),
+    Rgb([255, 120, 255]),
+    Rgb([255, 150, 40]),
+    Rgb([80, 120, 255]),
+    Rgb([0, 255, 200]),
+];
+
+/// Оформление аннотаций
+#[derive(Debug, Clone)]
+pub struct Annotator {
+    /// Цвет рамок областей без трека
+    pub box_color: Rgb<u8>,
+    pub text_color: Rgb<u8>,
+    pub label_background: Rgb<u8>,
+    /// Масштаб шрифта (1 - символ 5x7 пикселей)
+    pub scale: u32,
+    pub line_thickness: u32,
+    /// Сколько последних точек траектории рисовать (0 - не рисовать)
+    pub trail_length: usize,
+    /// Номер кадра и метка времени в левом верхнем углу
+    pub show_frame_info: bool,
+}
+
+impl Default for Annotator {
+    fn default() -> Self {
+        Self {
+            box_color: Rgb([0, 255, 0]),
+            text_color: Rgb([255, 255, 255]),
+            label_background: Rgb([0, 0, 0]),
+            scale: 2,
+            line_thickness: 2,
+            trail_length: 30,
+            show_frame_info: true,
+        }
+    }
+}
+
+impl Annotator {
+    pub fn track_color(id: u64) -> Rgb<u8> {
+        TRACK_COLORS[(id as usize).wrapping_sub(1) % TRACK_COLORS.len()]
+    }
+
+    /// Рисует области, треки и подпись кадра на изображении
+    pub fn annotate(
+        &self,
+        image: &mut RgbImage,
+        frame_number: usize,
+        timestamp: f64,
+        regions: &[MotionRegion],
+        tracks: &[Track],
+    ) {
+        for region in regions {
+            let tracked = tracks.iter().any(|t| t.is_visible() && t.region == *region);
+            if !tracked {
+                draw_rect(image, region.x as i64, region.y as i64, region.width, region.height, self.box_color, self.line_thickness);
+            }
+        }
+
+        for track in tracks {
+            let color = Self::track_color(track.id);
+            if self.trail_length > 0 {
+                let start = track.trail.len().saturating_sub(self.trail_length);
+                for pair in track.trail[start..].windows(2) {
+                    draw_line(
+                        image,
+                        (pair[0].x.round() as i64, pair[0].y.round() as i64),
+                        (pair[1].x.round() as i64, pair[1].y.round() as i64),
+                        color,
+                    );
+                }
+            }
+            if !track.is_visible() {
+                continue;
+            }
+
+            let region = &track.region;
+            draw_rect(image, region.x as i64, region.y as i64, region.width, region.height, color, self.line_thickness);
+            // Номер трека над рамкой, а если места нет - внутри нее
+            let label_height = ((GLYPH_HEIGHT + 2) * self.scale) as i64;
+            let label_y = if region.y as i64 >= label_height { region.y as i64 - label_height } else { region.y as i64 };
+            draw_label(image, region.x as i64, label_y, &format!("#{}", track.id), self.text_color, color, self.scale);
+        }
+
+        if self.show_frame_info {
+            let info = format!("FRAME {}  T={:.3}S", frame_number, timestamp);
+            draw_label(image, 0, 0, &info, self.text_color, self.label_background, self.scale);
+        }
+    }
+
+    pub fn annotate_frame(&self, frame: &mut VideoFrame, regions: &[MotionRegion], tracks: &[Track]) {
+        let (frame_number, timestamp) = (frame.frame_number, frame.timestamp);
+        self.annotate(&mut frame.data, frame_number, timestamp, regions, tracks);
+    }
+}
+
+/// Полный цикл аннотирования: поиск областей на производной, трекинг и
+/// отрисовка на выбранном кадре - самой производной или исходнике
+#[derive(Debug, Clone, Default)]
+pub struct MotionAnnotator {
+    pub detector: RegionDetector,
+    pub tracker: MotionTracker,
+    pub annotator: Annotator,
+}
+
+impl MotionAnnotator {
+    pub fn new(detector: RegionDetector, tracker: MotionTracker, annotator: Annotator) -> Self {
+        Self {
+            detector,
+            tracker,
+            annotator,
+        }
+    }
+
+    /// Обрабатывает очередную производную и рисует результат на `target`
+    /// (того же размера). Вызывать по порядку кадров.
+    pub fn process(&mut self, target: &mut VideoFrame, derivative: &VideoFrame) -> Vec<MotionRegion> {
+        let regions = self.detector.detect(&derivative.data);
+        self.tracker.update(derivative.frame_number, derivative.timestamp, &regions);
+        self.annotator.annotate_frame(target, &regions, self.tracker.tracks());
+        regions
+    }
+
+    /// Рисует аннотации прямо на производной, в которой ищутся области
+    pub fn process_in_place(&mut self, derivative: &mut VideoFrame) -> Vec<MotionRegion> {
+        let regions = self.detector.detect(&derivative.data);
+        self.tracker.update(derivative.frame_number, derivative.timestamp, &regions);
+        self.annotator.annotate_frame(derivative, &regions, self.tracker.tracks());
+        regions
+    }
+
+    /// Аннотирует набор кадров перед `save_frames_to_video`. `targets` - те же
+    /// производные или исходные кадры, по одному на каждую производную.
+    pub fn annotate_frames(&mut self, targets: &mut [VideoFrame], derivatives: &[VideoFrame]) {
+        for (target, derivative) in targets.iter_mut().zip(derivatives) {
+            self.process(target, derivative);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::render::font::{glyph, text_width, ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use image::{Rgb, RgbImage};
+
+/// Закрашивает пиксель, если он внутри кадра
+fn plot(image: &mut RgbImage, x: i64, y: i64, color: Rgb<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+        image.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+/// Закрашенный прямоугольник (обрезается по границам кадра)
+pub fn fill_rect(image: &mut RgbImage, x: i64, y: i64, width: u32, height: u32, color: Rgb<u8>) {
+    let x_start = x.max(0);
+    let y_start = y.max(0);
+    let x_end = (x + width as i64).min(image.width() as i64);
+    let y_end = (y + height as i64).min(image.height() as i64);
+    for py in y_start..y_end {
+        for px in x_start..x_end {
+            image.put_pixel(px as u32, py as u32, color);
+        }
+    }
+}
+
+/// Контур прямоугольника толщиной `thickness` внутрь
+pub fn draw_rect(image: &mut RgbImage, x: i64, y: i64, width: u32, height: u32, color: Rgb<u8>, thickness: u32) {
+    let t = thickness.min(width.div_ceil(2)).min(height.div_ceil(2)).max(1);
+    fill_rect(image, x, y, width, t, color);
+    fill_rect(image, x, y + height as i64 - t as i64, width, t, color);
+    fill_rect(image, x, y, t, height, color);
+    fill_rect(image, x + width as i64 - t as i64, y, t, height, color);
+}
+
+/// Отрезок по алгоритму Брезенхэма
+pub fn draw_line(image: &mut RgbImage, from: (i64, i64), to: (i64, i64), color: Rgb<u8>) {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let step_x = if x < to.0 { 1 } else { -1 };
+    let step_y = if y < to.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    loop {
+        plot(image, x, y, color);
+        if (x, y) == to {
+            break;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Текст встроенным шрифтом; `(x, y)` - левый верхний угол
+pub fn draw_text(image: &mut RgbImage, x: i64, y: i64, text: &str, color: Rgb<u8>, scale: u32) {
+    let scale = scale.max(1);
+    for (i, c) in text.chars().enumerate() {
+        let origin_x = x + (i as u32 * ADVANCE * scale) as i64;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    fill_rect(
+                        image,
+                        origin_x + (column * scale) as i64,
+                        y + (row as u32 * scale) as i64,
+                        scale,
+                        scale,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Текст на подложке, чтобы подпись читалась на любом фоне
+pub fn draw_label(
+    image: &mut RgbImage,
+    x: i64,
+    y: i64,
+    text: &str,
+    color: Rgb<u8>,
+    background: Rgb<u8>,
+    scale: u32,
+) {
+    let scale = scale.max(1);
+    let padding = scale as i64;
+    fill_rect(
+        image,
+        x,
+        y,
+        text_width(text, scale) + 2 * scale,
+        GLYPH_HEIGHT * scale + 2 * scale,
+        background,
+    );
+    draw_text(image, x + padding, y + padding, text, color, scale);
+}
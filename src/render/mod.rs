@@ -0,0 +1,5 @@
+pub mod font;
+pub mod draw;
+pub mod annotate;
+pub use annotate::{Annotator, MotionAnnotator};
+pub use draw::{draw_label, draw_line, draw_rect, draw_text, fill_rect};
@@ -0,0 +1,111 @@
+use crate::types::pixel::{FrameBuffer, FramePixel, Sample};
+use crate::types::VideoFrame;
+use image::{Pixel, Rgb};
+use std::marker::PhantomData;
+
+/// Тип знаковой разницы для компонент пикселя `P`
+pub type DeltaOf<P> = <<P as Pixel>::Subpixel as Sample>::Delta;
+
+/// Знаковая производная кадра: разница `текущий - предыдущий` по каждому каналу
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedDerivative<P: FramePixel = Rgb<u8>> {
+    pub data: Vec<DeltaOf<P>>,
+    pub width: u32,
+    pub height: u32,
+    pub timestamp: f64,
+    pub frame_number: usize,
+    _pixel: PhantomData<P>,
+}
+
+impl<P: FramePixel> SignedDerivative<P> {
+    pub fn new(data: Vec<DeltaOf<P>>, width: u32, height: u32, frame_number: usize, timestamp: f64) -> Self {
+        Self {
+            data,
+            width,
+            height,
+            timestamp,
+            frame_number,
+            _pixel: PhantomData,
+        }
+    }
+
+    /// Вычисляет знаковую разницу между двумя кадрами одинакового размера
+    pub fn between(previous: &FrameBuffer<P>, current: &FrameBuffer<P>, frame_number: usize, timestamp: f64) -> Self {
+        let data = current
+            .as_raw()
+            .iter()
+            .zip(previous.as_raw())
+            .map(|(&cur, &prev)| Sample::delta(cur, prev))
+            .collect();
+
+        Self::new(data, current.width(), current.height(), frame_number, timestamp)
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Количество ненулевых компонент (оценка разреженности)
+    pub fn nonzero_count(&self) -> usize {
+        let zero = DeltaOf::<P>::default();
+        self.data.iter().filter(|&&d| d != zero).count()
+    }
+
+    /// Обнуляет компоненты, модуль которых не превышает порог (в 8-битных единицах)
+    pub fn apply_threshold(&mut self, threshold: u8) {
+        let threshold = P::Subpixel::threshold_from_u8(threshold);
+        for d in self.data.iter_mut() {
+            if P::Subpixel::delta_abs(*d) <= threshold {
+                *d = DeltaOf::<P>::default();
+            }
+        }
+    }
+
+    /// Прибавляет производную к кадру с насыщением в допустимом диапазоне
+    pub fn apply_to(&self, base: &FrameBuffer<P>) -> FrameBuffer<P> {
+        let mut result = base.clone();
+        for (value, &d) in result.iter_mut().zip(&self.data) {
+            *value = value.apply_delta(d);
+        }
+        result
+    }
+
+    /// Визуализация производной: середина диапазона - нет изменений,
+    /// светлее/темнее - рост/падение яркости
+    pub fn to_visual(&self) -> FrameBuffer<P> {
+        let data = self
+            .data
+            .iter()
+            .map(|&d| P::Subpixel::from_unit(0.5 + P::Subpixel::delta_to_unit(d) / 2.0))
+            .collect();
+        FrameBuffer::<P>::from_raw(self.width, self.height, data)
+            .expect("размер данных производной соответствует ее размерам")
+    }
+}
+
+/// Элемент потока производных: опорный кадр или знаковая разница к предыдущему
+#[derive(Debug, Clone)]
+pub enum DerivativePacket<P: FramePixel = Rgb<u8>> {
+    Keyframe(VideoFrame<P>),
+    Delta(SignedDerivative<P>),
+}
+
+impl<P: FramePixel> DerivativePacket<P> {
+    pub fn frame_number(&self) -> usize {
+        match self {
+            DerivativePacket::Keyframe(frame) => frame.frame_number,
+            DerivativePacket::Delta(derivative) => derivative.frame_number,
+        }
+    }
+
+    pub fn timestamp(&self) -> f64 {
+        match self {
+            DerivativePacket::Keyframe(frame) => frame.timestamp,
+            DerivativePacket::Delta(derivative) => derivative.timestamp,
+        }
+    }
+
+    pub fn is_keyframe(&self) -> bool {
+        matches!(self, DerivativePacket::Keyframe(_))
+    }
+}
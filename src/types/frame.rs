@@ -1,14 +1,28 @@
-use image::{RgbImage};
+use crate::error::Error;
+use crate::types::geometry::{GeometryConfig, ResizeMode};
+use crate::types::pixel::{FrameBuffer, FramePixel, PixelFormat};
+use image::{EncodableLayout, PixelWithColorType, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
+/// Кадр видео. По умолчанию 8-битный RGB, но поддерживаются и другие
+/// форматы пикселей (`Luma<u8>`, `Rgba<u8>`, `Rgb<u16>`, `Luma<f32>` и т.д.)
 #[derive(Debug, Clone)]
-pub struct VideoFrame {
-    pub data: RgbImage,
+pub struct VideoFrame<P: FramePixel = Rgb<u8>> {
+    pub data: FrameBuffer<P>,
     pub timestamp: f64,
     pub frame_number: usize,
 }
 
 impl VideoFrame {
     pub fn new(data: RgbImage, frame_number: usize, timestamp: f64) -> Self {
+        Self::from_buffer(data, frame_number, timestamp)
+    }
+}
+
+impl<P: FramePixel> VideoFrame<P> {
+    /// Создает кадр произвольного формата пикселей
+    pub fn from_buffer(data: FrameBuffer<P>, frame_number: usize, timestamp: f64) -> Self {
         Self {
             data,
             frame_number,
@@ -28,28 +42,132 @@ impl VideoFrame {
         (self.data.width(), self.data.height())
     }
 
+    pub fn pixel_format(&self) -> PixelFormat {
+        P::FORMAT
+    }
+
     /// Сохраняет кадр в файл
-    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), image::ImageError> {
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), image::ImageError>
+    where
+        P: PixelWithColorType,
+        [P::Subpixel]: EncodableLayout,
+    {
         self.data.save(path)
     }
 }
 
-#[derive(Debug, Clone)]
+/// Способ вычисления разницы кадров
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DifferenceBackend {
+    /// Попиксельный обход через `get_pixel` (эталонная реализация)
+    PerPixel,
+    /// Обход непрерывных срезов компонент, удобный для автовекторизации
+    Slice,
+    /// Срезовый обход, распределенный по строкам между потоками пула rayon
+    #[default]
+    Parallel,
+}
+
+impl FromStr for DifferenceBackend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "per-pixel" | "pixel" => Ok(DifferenceBackend::PerPixel),
+            "slice" => Ok(DifferenceBackend::Slice),
+            "parallel" => Ok(DifferenceBackend::Parallel),
+            other => Err(Error::config(format!(
+                "Неизвестный способ вычисления разницы: {} (доступны per-pixel, slice, parallel)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Нормировка производной по времени между кадрами
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeNormalization {
+    /// Сырая разница соседних кадров
+    #[default]
+    None,
+    /// Разница делится на интервал между кадрами (изменение в секунду)
+    /// и умножается на `scale`. При `scale = 1/30` видео с 30 к/с дает
+    /// ту же величину, что и без нормировки.
+    PerSecond { scale: f32 },
+}
+
+/// Параметры обработки. В файле конфигурации отсутствующие поля берутся
+/// из значений по умолчанию, неизвестные считаются ошибкой.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ProcessingConfig{
     pub threshold:u8,
+    /// Частота кадров; для видео CLI берет ее из файла, если `--fps` не задан
     pub fps: u32,
+    /// Размер выхода; используется, если `geometry.resize` не `ResizeMode::None`
     pub output_width:u32,
     pub output_height: u32,
-    pub noise_reduction:bool
+    pub noise_reduction:bool,
+    /// Период опорных кадров для знаковых производных (0 - только первый кадр)
+    pub keyframe_interval: usize,
+    /// Реализация вычисления разницы кадров
+    pub backend: DifferenceBackend,
+    /// Нормировка производной по реальному интервалу между кадрами
+    pub time_normalization: TimeNormalization,
+    /// Обрезка и масштабирование кадров до `output_width` x `output_height`
+    pub geometry: GeometryConfig,
 }
+impl ProcessingConfig {
+    /// Порог шумоподавления по умолчанию
+    pub const DEFAULT_THRESHOLD: u8 = 30;
+    /// Частота кадров по умолчанию
+    pub const DEFAULT_FPS: u32 = 30;
+
+    /// Целевой размер кадра для `geometry`
+    pub fn output_size(&self) -> (u32, u32) {
+        (self.output_width, self.output_height)
+    }
+
+    /// Проверяет значения, которые нельзя отсечь типами (например, из
+    /// файла конфигурации)
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.fps == 0 {
+            return Err(Error::config("Частота кадров должна быть больше нуля"));
+        }
+        if self.geometry.resize != ResizeMode::None && (self.output_width == 0 || self.output_height == 0) {
+            return Err(Error::config(format!(
+                "Размер выхода {}x{} недопустим при масштабировании",
+                self.output_width, self.output_height
+            )));
+        }
+        if let Some(crop) = self.geometry.crop
+            && (crop.width == 0 || crop.height == 0)
+        {
+            return Err(Error::config(format!("Пустая область обрезки {}x{}", crop.width, crop.height)));
+        }
+        if let TimeNormalization::PerSecond { scale } = self.time_normalization
+            && !(scale.is_finite() && scale > 0.0)
+        {
+            return Err(Error::config(format!("Масштаб нормировки должен быть положительным: {}", scale)));
+        }
+        Ok(())
+    }
+}
+
 impl Default for ProcessingConfig{
     fn default() -> Self {
         Self{
-            threshold:32,
-            fps: 32,
+            threshold: Self::DEFAULT_THRESHOLD,
+            fps: Self::DEFAULT_FPS,
             output_width:640,
             output_height:480,
-            noise_reduction:true
+            noise_reduction:true,
+            keyframe_interval: 30,
+            backend: DifferenceBackend::default(),
+            time_normalization: TimeNormalization::default(),
+            geometry: GeometryConfig::default(),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,253 @@
+//! Геометрия выходных кадров: обрезка и масштабирование до
+//! `ProcessingConfig::output_width` x `output_height`.
+
+use crate::error::Error;
+use crate::types::pixel::{FrameBuffer, FramePixel};
+use crate::types::VideoFrame;
+use image::imageops::{self, FilterType};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Как вписать кадр в `output_width` x `output_height`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeMode {
+    /// Исходный размер, `output_width`/`output_height` не используются
+    #[default]
+    None,
+    /// Вписать с сохранением пропорций; размер может быть меньше заданного
+    Fit,
+    /// Заполнить целиком с сохранением пропорций, лишнее обрезается по центру
+    Fill,
+    /// Растянуть до точного размера без сохранения пропорций
+    Stretch,
+    /// Вписать с сохранением пропорций и дополнить черными полями
+    Pad,
+}
+
+impl FromStr for ResizeMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "original" => Ok(ResizeMode::None),
+            "fit" => Ok(ResizeMode::Fit),
+            "fill" | "cover" => Ok(ResizeMode::Fill),
+            "stretch" => Ok(ResizeMode::Stretch),
+            "pad" | "letterbox" => Ok(ResizeMode::Pad),
+            other => Err(Error::config(format!(
+                "Неизвестный режим масштабирования: {} (доступны none, fit, fill, stretch, pad)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Фильтр интерполяции при масштабировании
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+    Bicubic,
+    Gaussian,
+    Lanczos,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Bilinear => FilterType::Triangle,
+            ResizeFilter::Bicubic => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl FromStr for ResizeFilter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nearest" | "neighbor" => Ok(ResizeFilter::Nearest),
+            "bilinear" | "triangle" => Ok(ResizeFilter::Bilinear),
+            "bicubic" | "catmull-rom" => Ok(ResizeFilter::Bicubic),
+            "gaussian" => Ok(ResizeFilter::Gaussian),
+            "lanczos" | "lanczos3" => Ok(ResizeFilter::Lanczos),
+            other => Err(Error::config(format!(
+                "Неизвестный фильтр: {} (доступны nearest, bilinear, bicubic, gaussian, lanczos)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Когда менять геометрию
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GeometryStage {
+    /// До вычисления разницы: меньше пикселей - быстрее обработка
+    #[default]
+    #[serde(rename = "before")]
+    BeforeDifference,
+    /// После: разница считается в полном разрешении, меняется только выход
+    #[serde(rename = "after")]
+    AfterDifference,
+}
+
+impl FromStr for GeometryStage {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "before" => Ok(GeometryStage::BeforeDifference),
+            "after" => Ok(GeometryStage::AfterDifference),
+            other => Err(Error::config(format!("Неизвестный этап: {} (доступны before, after)", other))),
+        }
+    }
+}
+
+/// Прямоугольник обрезки в координатах исходного кадра
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Пересечение с кадром `width`x`height`
+    fn clamp_to(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let x = self.x.min(width.saturating_sub(1));
+        let y = self.y.min(height.saturating_sub(1));
+        (x, y, self.width.min(width - x).max(1), self.height.min(height - y).max(1))
+    }
+}
+
+/// Формат как у фильтра `crop` FFmpeg: `ширина:высота:x:y`; без `x:y`
+/// обрезка идет от левого верхнего угла
+impl FromStr for CropRect {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::config(format!("Некорректная обрезка: {} (ожидается ширина:высота[:x:y])", s));
+        let values = s
+            .split(':')
+            .map(|part| part.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let rect = match values[..] {
+            [width, height] => CropRect::new(0, 0, width, height),
+            [width, height, x, y] => CropRect::new(x, y, width, height),
+            _ => return Err(invalid()),
+        };
+        if rect.width == 0 || rect.height == 0 {
+            return Err(invalid());
+        }
+        Ok(rect)
+    }
+}
+
+/// Обрезка и масштабирование кадров. Сначала применяется обрезка, затем
+/// масштабирование до `output_width` x `output_height` из `ProcessingConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeometryConfig {
+    pub resize: ResizeMode,
+    pub filter: ResizeFilter,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<CropRect>,
+    pub stage: GeometryStage,
+}
+
+impl GeometryConfig {
+    /// Геометрия кадров не меняется
+    pub fn is_identity(&self) -> bool {
+        self.resize == ResizeMode::None && self.crop.is_none()
+    }
+
+    /// Размер выходного кадра для входа `width`x`height` и целевого
+    /// размера `target`. В режиме `Fit` размер округляется до четного,
+    /// как этого требуют кодеки с субдискретизацией цвета 4:2:0.
+    pub fn output_dimensions(&self, width: u32, height: u32, target: (u32, u32)) -> (u32, u32) {
+        let (width, height) = match self.crop {
+            Some(crop) => {
+                let (_, _, w, h) = crop.clamp_to(width, height);
+                (w, h)
+            }
+            None => (width, height),
+        };
+        match self.resize {
+            ResizeMode::None => (width, height),
+            ResizeMode::Fill | ResizeMode::Stretch | ResizeMode::Pad => target,
+            ResizeMode::Fit => {
+                let (w, h) = fit_inside(width, height, target);
+                (even(w), even(h))
+            }
+        }
+    }
+
+    /// Применяет обрезку и масштабирование к кадру
+    pub fn apply<P: FramePixel>(&self, frame: &VideoFrame<P>, target: (u32, u32)) -> VideoFrame<P> {
+        let cropped = self.crop.map(|crop| {
+            let (x, y, w, h) = crop.clamp_to(frame.width(), frame.height());
+            imageops::crop_imm(&frame.data, x, y, w, h).to_image()
+        });
+        let source = cropped.as_ref().unwrap_or(&frame.data);
+        let target = (target.0.max(1), target.1.max(1));
+
+        let data = match self.resize {
+            ResizeMode::None => cropped.unwrap_or_else(|| frame.data.clone()),
+            ResizeMode::Stretch => self.resize_to(source, target),
+            ResizeMode::Fit => {
+                let size = self.output_dimensions(frame.width(), frame.height(), target);
+                self.resize_to(source, size)
+            }
+            ResizeMode::Pad => {
+                let (w, h) = fit_inside(source.width(), source.height(), target);
+                let scaled = self.resize_to(source, (w, h));
+                let mut canvas = FrameBuffer::<P>::new(target.0, target.1);
+                imageops::replace(&mut canvas, &scaled, ((target.0 - w) / 2) as i64, ((target.1 - h) / 2) as i64);
+                canvas
+            }
+            ResizeMode::Fill => {
+                let scale = f64::max(
+                    target.0 as f64 / source.width() as f64,
+                    target.1 as f64 / source.height() as f64,
+                );
+                let w = ((source.width() as f64 * scale).round() as u32).max(target.0);
+                let h = ((source.height() as f64 * scale).round() as u32).max(target.1);
+                let scaled = self.resize_to(source, (w, h));
+                imageops::crop_imm(&scaled, (w - target.0) / 2, (h - target.1) / 2, target.0, target.1).to_image()
+            }
+        };
+        VideoFrame::from_buffer(data, frame.frame_number, frame.timestamp)
+    }
+
+    fn resize_to<P: FramePixel>(&self, image: &FrameBuffer<P>, (width, height): (u32, u32)) -> FrameBuffer<P> {
+        if image.dimensions() == (width, height) {
+            return image.clone();
+        }
+        imageops::resize(image, width, height, self.filter.filter_type())
+    }
+}
+
+/// Наибольший размер с пропорциями `width`:`height`, помещающийся в `target`
+fn fit_inside(width: u32, height: u32, target: (u32, u32)) -> (u32, u32) {
+    let scale = f64::min(target.0 as f64 / width as f64, target.1 as f64 / height as f64);
+    let w = ((width as f64 * scale).round() as u32).clamp(1, target.0);
+    let h = ((height as f64 * scale).round() as u32).clamp(1, target.1);
+    (w, h)
+}
+
+fn even(value: u32) -> u32 {
+    if value > 1 { value & !1 } else { value }
+}
@@ -1,2 +1,8 @@
 pub mod frame;
-pub use frame::{VideoFrame, ProcessingConfig};
\ No newline at end of file
+pub mod derivative;
+pub mod pixel;
+pub mod geometry;
+pub use frame::{VideoFrame, ProcessingConfig, DifferenceBackend, TimeNormalization};
+pub use derivative::{SignedDerivative, DerivativePacket};
+pub use pixel::{FrameBuffer, FramePixel, PixelFormat, Sample};
+pub use geometry::{CropRect, GeometryConfig, GeometryStage, ResizeFilter, ResizeMode};
@@ -0,0 +1,270 @@
+use image::{ImageBuffer, Luma, Pixel, Primitive, Rgb, Rgba};
+use std::fmt::Debug;
+
+/// Буфер изображения для пикселя `P`
+pub type FrameBuffer<P> = ImageBuffer<P, Vec<<P as Pixel>::Subpixel>>;
+
+/// Тип компоненты пикселя, над которым умеет работать процессор.
+/// Для целых типов разница хранится в более широком знаковом типе, поэтому
+/// восстановление без потерь. Для `f32` разница и восстановление выполняются
+/// в той же точности и могут отличаться от исходника на ошибку округления.
+pub trait Sample: Primitive + Debug + Send + Sync + 'static {
+    type Delta: Copy + Default + PartialEq + Debug + Send + Sync + 'static;
+
+    /// Размер компоненты в байтах при сериализации
+    const BYTES: usize;
+
+    /// Знаковая разница `current - previous`
+    fn delta(current: Self, previous: Self) -> Self::Delta;
+
+    /// Прибавляет разницу с насыщением в допустимом диапазоне
+    fn apply_delta(self, delta: Self::Delta) -> Self;
+
+    /// Модуль разницы в единицах компоненты
+    fn delta_abs(delta: Self::Delta) -> Self;
+
+    /// Разница в долях полного диапазона (-1.0..=1.0)
+    fn delta_to_unit(delta: Self::Delta) -> f32;
+
+    /// Переводит порог, заданный в 8-битных единицах, в единицы компоненты
+    fn threshold_from_u8(threshold: u8) -> Self;
+
+    /// Значение в долях полного диапазона (0.0..=1.0)
+    fn to_unit(self) -> f32;
+
+    /// Значение из долей полного диапазона с насыщением
+    fn from_unit(value: f32) -> Self;
+
+    /// Кодирует разницу в беззнаковое число (zigzag для целых)
+    fn encode_delta(delta: Self::Delta) -> u64;
+
+    fn decode_delta(value: u64) -> Self::Delta;
+
+    fn write_le(self, out: &mut Vec<u8>);
+
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+impl Sample for u8 {
+    type Delta = i16;
+    const BYTES: usize = 1;
+
+    fn delta(current: Self, previous: Self) -> i16 {
+        current as i16 - previous as i16
+    }
+
+    fn apply_delta(self, delta: i16) -> Self {
+        (self as i16 + delta).clamp(0, u8::MAX as i16) as u8
+    }
+
+    fn delta_abs(delta: i16) -> Self {
+        delta.unsigned_abs().min(u8::MAX as u16) as u8
+    }
+
+    fn delta_to_unit(delta: i16) -> f32 {
+        delta as f32 / u8::MAX as f32
+    }
+
+    fn threshold_from_u8(threshold: u8) -> Self {
+        threshold
+    }
+
+    fn to_unit(self) -> f32 {
+        self as f32 / u8::MAX as f32
+    }
+
+    fn from_unit(value: f32) -> Self {
+        (value.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8
+    }
+
+    fn encode_delta(delta: i16) -> u64 {
+        ((delta << 1) ^ (delta >> 15)) as u16 as u64
+    }
+
+    fn decode_delta(value: u64) -> i16 {
+        let value = value as u16;
+        ((value >> 1) as i16) ^ -((value & 1) as i16)
+    }
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+}
+
+impl Sample for u16 {
+    type Delta = i32;
+    const BYTES: usize = 2;
+
+    fn delta(current: Self, previous: Self) -> i32 {
+        current as i32 - previous as i32
+    }
+
+    fn apply_delta(self, delta: i32) -> Self {
+        (self as i32 + delta).clamp(0, u16::MAX as i32) as u16
+    }
+
+    fn delta_abs(delta: i32) -> Self {
+        delta.unsigned_abs().min(u16::MAX as u32) as u16
+    }
+
+    fn delta_to_unit(delta: i32) -> f32 {
+        delta as f32 / u16::MAX as f32
+    }
+
+    fn threshold_from_u8(threshold: u8) -> Self {
+        // 255 * 257 = 65535: порог масштабируется на полный 16-битный диапазон
+        threshold as u16 * 257
+    }
+
+    fn to_unit(self) -> f32 {
+        self as f32 / u16::MAX as f32
+    }
+
+    fn from_unit(value: f32) -> Self {
+        (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+    }
+
+    fn encode_delta(delta: i32) -> u64 {
+        ((delta << 1) ^ (delta >> 31)) as u32 as u64
+    }
+
+    fn decode_delta(value: u64) -> i32 {
+        let value = value as u32;
+        ((value >> 1) as i32) ^ -((value & 1) as i32)
+    }
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    }
+}
+
+/// Приближенная реализация: `previous + (current - previous)` не обязательно
+/// равно `current` в арифметике с плавающей точкой
+impl Sample for f32 {
+    type Delta = f32;
+    const BYTES: usize = 4;
+
+    fn delta(current: Self, previous: Self) -> f32 {
+        current - previous
+    }
+
+    fn apply_delta(self, delta: f32) -> Self {
+        self + delta
+    }
+
+    fn delta_abs(delta: f32) -> Self {
+        delta.abs()
+    }
+
+    fn delta_to_unit(delta: f32) -> f32 {
+        delta
+    }
+
+    fn threshold_from_u8(threshold: u8) -> Self {
+        threshold as f32 / u8::MAX as f32
+    }
+
+    fn to_unit(self) -> f32 {
+        self
+    }
+
+    fn from_unit(value: f32) -> Self {
+        value.clamp(0.0, 1.0)
+    }
+
+    fn encode_delta(delta: f32) -> u64 {
+        delta.to_bits() as u64
+    }
+
+    fn decode_delta(value: u64) -> f32 {
+        f32::from_bits(value as u32)
+    }
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
+
+/// Формат пикселей кадра
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgb8,
+    Rgba8,
+    Luma8,
+    Rgb16,
+    Rgba16,
+    Luma16,
+    Rgb32F,
+    Luma32F,
+}
+
+impl PixelFormat {
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::Luma8 | PixelFormat::Luma16 | PixelFormat::Luma32F => 1,
+            PixelFormat::Rgb8 | PixelFormat::Rgb16 | PixelFormat::Rgb32F => 3,
+            PixelFormat::Rgba8 | PixelFormat::Rgba16 => 4,
+        }
+    }
+
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            PixelFormat::Rgb8 | PixelFormat::Rgba8 | PixelFormat::Luma8 => 1,
+            PixelFormat::Rgb16 | PixelFormat::Rgba16 | PixelFormat::Luma16 => 2,
+            PixelFormat::Rgb32F | PixelFormat::Luma32F => 4,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        self.channels() * self.bytes_per_sample()
+    }
+}
+
+/// Пиксель, поддерживаемый кадрами и процессором производных
+pub trait FramePixel: Pixel<Subpixel: Sample> + Debug + Send + Sync + 'static {
+    const FORMAT: PixelFormat;
+}
+
+impl FramePixel for Rgb<u8> {
+    const FORMAT: PixelFormat = PixelFormat::Rgb8;
+}
+
+impl FramePixel for Rgba<u8> {
+    const FORMAT: PixelFormat = PixelFormat::Rgba8;
+}
+
+impl FramePixel for Luma<u8> {
+    const FORMAT: PixelFormat = PixelFormat::Luma8;
+}
+
+impl FramePixel for Rgb<u16> {
+    const FORMAT: PixelFormat = PixelFormat::Rgb16;
+}
+
+impl FramePixel for Rgba<u16> {
+    const FORMAT: PixelFormat = PixelFormat::Rgba16;
+}
+
+impl FramePixel for Luma<u16> {
+    const FORMAT: PixelFormat = PixelFormat::Luma16;
+}
+
+impl FramePixel for Rgb<f32> {
+    const FORMAT: PixelFormat = PixelFormat::Rgb32F;
+}
+
+impl FramePixel for Luma<f32> {
+    const FORMAT: PixelFormat = PixelFormat::Luma32F;
+}
@@ -1,5 +1,5 @@
 use crate::types::VideoFrame;
-use image::{GenericImage, ImageBuffer, Rgb, RgbImage};
+use image::{ImageBuffer, Rgb, RgbImage};
 use rand::Rng;
 
 pub struct FrameGenerator{
@@ -16,7 +16,6 @@ impl FrameGenerator{
     pub fn generate_moving_object_frames(&self, num_frames: usize)->Vec<VideoFrame>{
 
         let mut frames = Vec::with_capacity(num_frames);
-        let mut rng = rand::thread_rng();
         for i in 0..num_frames{
             let timestamp = i as f64/ self.fps as f64;
             let mut frame = self.create_background();
@@ -106,14 +105,13 @@ impl FrameGenerator{
 
     }
     fn add_random_noise(&self, frame:&mut RgbImage, num_pixels:u32){
-        let mut rng = rand::thread_rng();
+        let mut rng = rand::rng();
         for _ in 0..num_pixels{
-            let x = rng.gen_range(0..self.width);
-            let y = rng.gen_range(0..self.width);
-            let r = rng.gen_range(0..=255);
-            let r = rng.gen_range(0..=255);
-            let g = rng.gen_range(0..=255);
-            let b = rng.gen_range(0..=255);
+            let x = rng.random_range(0..self.width);
+            let y = rng.random_range(0..self.height);
+            let r = rng.random_range(0..=255);
+            let g = rng.random_range(0..=255);
+            let b = rng.random_range(0..=255);
             frame.put_pixel(x, y, Rgb([r,g, b]))
         }
     }
@@ -0,0 +1,195 @@
+//! Анимированные GIF и APNG для коротких фрагментов: их можно вставить
+//! в задачу или чат без видеоплеера. Длительность каждого кадра берется
+//! из разницы меток времени, так что VFR-фрагменты не ускоряются.
+
+use crate::error::{Error, Result};
+use crate::types::VideoFrame;
+use crate::video::stream::VideoSink;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Длительность кадра в миллисекундах по соседним меткам времени
+fn frame_delay_ms(timestamp: f64, next_timestamp: Option<f64>, fps: u32) -> u32 {
+    let fallback = 1000.0 / fps.max(1) as f64;
+    let delay = match next_timestamp {
+        Some(next) if next > timestamp => (next - timestamp) * 1000.0,
+        _ => fallback,
+    };
+    delay.round().max(1.0) as u32
+}
+
+/// Приемник анимированного GIF. Каждый кадр сводится к палитре из 256
+/// цветов (NeuQuant), по желанию с диффузией ошибки Флойда-Стейнберга.
+/// Кадр записывается, когда приходит следующий и становится известна
+/// его длительность.
+pub struct GifSink<W: Write> {
+    writer: Option<W>,
+    encoder: Option<GifEncoder<W>>,
+    fps: u32,
+    speed: i32,
+    dithering: bool,
+    repeat: Repeat,
+    pending: Option<VideoFrame>,
+}
+
+impl GifSink<BufWriter<File>> {
+    pub fn create<Q: AsRef<Path>>(path: Q, fps: u32) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|e| Error::file(path, e))?;
+        Ok(Self::new(BufWriter::new(file), fps))
+    }
+}
+
+impl<W: Write> GifSink<W> {
+    pub fn new(writer: W, fps: u32) -> Self {
+        Self {
+            writer: Some(writer),
+            encoder: None,
+            fps,
+            speed: 10,
+            dithering: false,
+            repeat: Repeat::Infinite,
+            pending: None,
+        }
+    }
+
+    /// Скорость квантования 1..=30: 1 - лучшая палитра, 30 - самая быстрая
+    pub fn with_speed(mut self, speed: i32) -> Self {
+        self.speed = speed.clamp(1, 30);
+        self
+    }
+
+    /// Диффузия ошибки при сведении к палитре: плавнее градиенты тепловых
+    /// карт, но больше размер файла
+    pub fn with_dithering(mut self, dithering: bool) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Количество повторов; `None` - бесконечный цикл
+    pub fn with_loop_count(mut self, count: Option<u16>) -> Self {
+        self.repeat = count.map_or(Repeat::Infinite, Repeat::Finite);
+        self
+    }
+
+    fn encode(&mut self, frame: VideoFrame, next_timestamp: Option<f64>) -> Result<()> {
+        let mut rgba = DynamicImage::ImageRgb8(frame.data).to_rgba8();
+        if self.dithering {
+            // Палитра с диффузией; кодировщик возьмет ее цвета без повторного квантования
+            let palette = color_quant::NeuQuant::new(self.speed, 256, rgba.as_raw());
+            image::imageops::dither(&mut rgba, &palette);
+        }
+
+        // GIF хранит задержку в сотых долях секунды: округляем, а не отбрасываем остаток
+        let delay = (frame_delay_ms(frame.timestamp, next_timestamp, self.fps) + 5) / 10 * 10;
+        let encoder = self.encoder.as_mut().ok_or_else(|| Error::encode("Запись GIF уже завершена"))?;
+        encoder.encode_frame(Frame::from_parts(rgba, 0, 0, Delay::from_numer_denom_ms(delay.max(10), 1)))?;
+        Ok(())
+    }
+}
+
+impl<W: Write> VideoSink for GifSink<W> {
+    fn write_frame(&mut self, frame: &VideoFrame) -> Result<()> {
+        if frame.width() > u16::MAX as u32 || frame.height() > u16::MAX as u32 {
+            return Err(Error::encode(format!("GIF не поддерживает кадры {}x{}", frame.width(), frame.height())));
+        }
+        // Кодировщик создается при первом кадре, когда настройки уже заданы
+        if let Some(writer) = self.writer.take() {
+            let mut encoder = GifEncoder::new_with_speed(writer, self.speed);
+            encoder.set_repeat(self.repeat)?;
+            self.encoder = Some(encoder);
+        }
+        if let Some(previous) = self.pending.replace(frame.clone()) {
+            self.encode(previous, Some(frame.timestamp))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(last) = self.pending.take() {
+            self.encode(last, None)?;
+        }
+        // Завершающий блок GIF пишется при освобождении кодировщика
+        self.encoder.take();
+        Ok(())
+    }
+}
+
+/// Приемник анимированного PNG (без потерь). Формат требует заранее
+/// знать число кадров, поэтому кадры копятся в памяти до `finish`.
+pub struct ApngSink<W: Write> {
+    writer: Option<W>,
+    fps: u32,
+    loop_count: u32,
+    frames: Vec<VideoFrame>,
+}
+
+impl ApngSink<BufWriter<File>> {
+    pub fn create<Q: AsRef<Path>>(path: Q, fps: u32) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|e| Error::file(path, e))?;
+        Ok(Self::new(BufWriter::new(file), fps))
+    }
+}
+
+impl<W: Write> ApngSink<W> {
+    pub fn new(writer: W, fps: u32) -> Self {
+        Self {
+            writer: Some(writer),
+            fps,
+            loop_count: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Количество повторов; `None` - бесконечный цикл
+    pub fn with_loop_count(mut self, count: Option<u16>) -> Self {
+        self.loop_count = count.map_or(0, u32::from);
+        self
+    }
+}
+
+impl<W: Write> VideoSink for ApngSink<W> {
+    fn write_frame(&mut self, frame: &VideoFrame) -> Result<()> {
+        if let Some(first) = self.frames.first()
+            && first.dimensions() != frame.dimensions()
+        {
+            return Err(Error::DimensionMismatch {
+                expected: first.dimensions(),
+                actual: frame.dimensions(),
+            });
+        }
+        self.frames.push(frame.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        let Some(first) = self.frames.first() else {
+            return Ok(());
+        };
+
+        let mut encoder = png::Encoder::new(writer, first.width(), first.height());
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, self.loop_count).map_err(Error::encode)?;
+        let mut png_writer = encoder.write_header().map_err(Error::encode)?;
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            let next = self.frames.get(i + 1).map(|f| f.timestamp);
+            let delay = frame_delay_ms(frame.timestamp, next, self.fps).min(u16::MAX as u32);
+            png_writer.set_frame_delay(delay as u16, 1000).map_err(Error::encode)?;
+            png_writer.write_image_data(frame.data.as_raw()).map_err(Error::encode)?;
+        }
+        png_writer.finish().map_err(Error::encode)?;
+        self.frames.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Что делать со звуком исходного видео при записи результата.
+/// В файле конфигурации - `none`, `copy`, `aac` или `{ codec, bitrate }`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "AudioModeFields", into = "AudioModeFields")]
+pub enum AudioMode {
+    /// Беззвучный результат
+    #[default]
+    None,
+    /// Копировать звуковой поток без перекодирования
+    Copy,
+    /// Перекодировать (например, если исходный кодек не поддерживается контейнером)
+    Reencode { codec: String, bitrate: Option<String> },
+}
+
+impl AudioMode {
+    /// Перекодирование в AAC - формат, который принимают все распространенные контейнеры
+    pub fn aac() -> Self {
+        AudioMode::Reencode { codec: "aac".to_string(), bitrate: Some("192k".to_string()) }
+    }
+}
+
+/// `none`, `copy`, `aac` или кодек с битрейтом `libopus:96k`; без
+/// битрейта (`libopus:`) FFmpeg выбирает его сам
+impl FromStr for AudioMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let lower = s.to_ascii_lowercase();
+        if let Some((codec, bitrate)) = lower.split_once(':') {
+            if codec.is_empty() {
+                return Err(Error::config(format!("Не указан кодек звука: {}", s)));
+            }
+            let bitrate = (!bitrate.is_empty()).then(|| bitrate.to_string());
+            return Ok(AudioMode::Reencode { codec: codec.to_string(), bitrate });
+        }
+        match lower.as_str() {
+            "none" => Ok(AudioMode::None),
+            "copy" => Ok(AudioMode::Copy),
+            "aac" => Ok(AudioMode::aac()),
+            other => Err(Error::config(format!(
+                "Неизвестный режим звука: {} (доступны copy, aac, none или кодек:битрейт)",
+                other
+            ))),
+        }
+    }
+}
+
+/// В том же синтаксисе, что разбирает `FromStr`
+impl fmt::Display for AudioMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioMode::None => f.write_str("none"),
+            AudioMode::Copy => f.write_str("copy"),
+            mode if *mode == AudioMode::aac() => f.write_str("aac"),
+            AudioMode::Reencode { codec, bitrate } => write!(f, "{}:{}", codec, bitrate.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+/// `AudioMode` в файле конфигурации: имя режима или кодек с битрейтом
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum AudioModeFields {
+    Name(String),
+    Reencode {
+        codec: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bitrate: Option<String>,
+    },
+}
+
+impl TryFrom<AudioModeFields> for AudioMode {
+    type Error = Error;
+
+    fn try_from(fields: AudioModeFields) -> Result<Self> {
+        match fields {
+            AudioModeFields::Name(name) => name.parse(),
+            AudioModeFields::Reencode { codec, bitrate } => Ok(AudioMode::Reencode { codec, bitrate }),
+        }
+    }
+}
+
+impl From<AudioMode> for AudioModeFields {
+    fn from(mode: AudioMode) -> Self {
+        match mode {
+            AudioMode::None => AudioModeFields::Name("none".to_string()),
+            AudioMode::Copy => AudioModeFields::Name("copy".to_string()),
+            mode if mode == AudioMode::aac() => AudioModeFields::Name("aac".to_string()),
+            AudioMode::Reencode { codec, bitrate } => AudioModeFields::Reencode { codec, bitrate },
+        }
+    }
+}
+
+/// Звуковая дорожка, которую нужно добавить в выходное видео
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioTrack {
+    pub source_path: String,
+    pub mode: AudioMode,
+    /// Начало звука на шкале исходника в секундах. `None` - метка времени
+    /// первого записанного кадра, так звук совпадает с обрезанным видео.
+    pub start: Option<f64>,
+    /// Длительность звука; без нее звук обрезается по концу видео
+    pub duration: Option<f64>,
+}
+
+impl AudioTrack {
+    pub fn new(source_path: &str, mode: AudioMode) -> Self {
+        Self {
+            source_path: source_path.to_string(),
+            mode,
+            start: None,
+            duration: None,
+        }
+    }
+
+    pub fn with_range(mut self, start: Option<f64>, duration: Option<f64>) -> Self {
+        self.start = start;
+        self.duration = duration;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != AudioMode::None
+    }
+
+    /// Аргументы второго входа FFmpeg: смещение, длительность и путь к исходнику
+    pub(crate) fn input_args(&self, first_frame_timestamp: f64) -> Vec<String> {
+        let start = self.start.unwrap_or(first_frame_timestamp).max(0.0);
+        let mut args = Vec::new();
+        if start > 0.0 {
+            args.extend(["-ss".to_string(), format!("{:.6}", start)]);
+        }
+        if let Some(duration) = self.duration {
+            args.extend(["-t".to_string(), format!("{:.6}", duration)]);
+        }
+        args.extend(["-i".to_string(), self.source_path.clone()]);
+        args
+    }
+
+    /// Аргументы выхода: видео с первого входа, звук (если есть) со второго
+    pub(crate) fn output_args(&self, audio_input: usize) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "-map", "0:v:0",
+            // `?` - исходник без звука не считается ошибкой
+            "-map", &format!("{}:a?", audio_input),
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        match &self.mode {
+            AudioMode::None => {}
+            AudioMode::Copy => args.extend(["-c:a".to_string(), "copy".to_string()]),
+            AudioMode::Reencode { codec, bitrate } => {
+                args.extend(["-c:a".to_string(), codec.clone()]);
+                if let Some(bitrate) = bitrate {
+                    args.extend(["-b:a".to_string(), bitrate.clone()]);
+                }
+            }
+        }
+        args.push("-shortest".to_string());
+        args
+    }
+}
@@ -0,0 +1,339 @@
+//! Пакетная обработка: входы из директорий, glob-шаблонов и списков,
+//! выходы в зеркальной структуре директорий, пул потоков rayon и отчет.
+//!
+//! Результат пишется во временный файл `*.partial.*` и переименовывается
+//! только после успеха, поэтому готовым считается любой существующий выход,
+//! который новее входа: прерванный запуск можно просто повторить.
+
+use crate::error::{Error, Result};
+use crate::video::image_sequence::{natural_cmp, wildcard_match};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Расширения видеофайлов, которые ищутся в директориях
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "mov", "mkv", "webm", "avi", "mpg", "mpeg", "ts", "mts", "m2ts", "flv", "wmv", "y4m",
+];
+
+/// Входной файл и его путь относительно корня, который повторяется в
+/// директории результатов
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchInput {
+    pub path: PathBuf,
+    pub relative: PathBuf,
+}
+
+/// Видеофайлы из директории (рекурсивно), glob-шаблона в имени файла
+/// (`clips/cam_*.mp4`) или одного файла. Директория повторяется в выходе
+/// целиком, для шаблона и файла сохраняется только имя.
+pub fn collect_inputs(spec: impl AsRef<Path>) -> Result<Vec<BatchInput>> {
+    collect_with_prefix(spec.as_ref(), Path::new(""))
+}
+
+/// Входы из списка: по пути, директории или шаблону в строке, пустые строки
+/// и строки с `#` пропускаются. Относительные пути считаются от директории
+/// списка и повторяются в выходе вместе со своими поддиректориями.
+pub fn read_manifest(path: impl AsRef<Path>) -> Result<Vec<BatchInput>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|e| Error::file(path, e))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut inputs = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let entry = line.trim();
+        if entry.is_empty() || entry.starts_with('#') {
+            continue;
+        }
+        let entry = Path::new(entry);
+        // Каталог записи повторяется в выходе, если он не выходит за пределы списка
+        let prefix = match entry.parent() {
+            Some(parent) if entry.is_relative() && parent.components().all(|c| matches!(c, Component::Normal(_))) => {
+                if base.join(entry).is_dir() { entry } else { parent }
+            }
+            _ => Path::new(""),
+        };
+        let found = collect_with_prefix(&base.join(entry), prefix).map_err(|e| {
+            let message = match e {
+                Error::InvalidConfig(message) => message,
+                other => other.to_string(),
+            };
+            Error::config(format!("{}:{}: {}", path.display(), number + 1, message))
+        })?;
+        inputs.extend(found);
+    }
+    Ok(inputs)
+}
+
+fn collect_with_prefix(spec: &Path, prefix: &Path) -> Result<Vec<BatchInput>> {
+    let name = spec.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+    if spec.is_dir() {
+        let mut files = Vec::new();
+        walk(spec, &mut files)?;
+        sort_paths(&mut files);
+        return Ok(files
+            .into_iter()
+            .map(|path| {
+                let relative = prefix.join(path.strip_prefix(spec).unwrap_or(&path));
+                BatchInput { path, relative }
+            })
+            .collect());
+    }
+
+    if name.contains(['*', '?']) {
+        let directory = spec.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let mut files: Vec<PathBuf> = fs::read_dir(directory)
+            .map_err(|e| Error::file(directory, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.is_file())
+            .filter(|file| wildcard_match(&name, &file.file_name().unwrap_or_default().to_string_lossy()))
+            .collect();
+        sort_paths(&mut files);
+        return Ok(files
+            .into_iter()
+            .map(|path| {
+                let relative = prefix.join(path.file_name().unwrap_or_default());
+                BatchInput { path, relative }
+            })
+            .collect());
+    }
+
+    if spec.is_file() {
+        return Ok(vec![BatchInput { path: spec.to_path_buf(), relative: prefix.join(&name) }]);
+    }
+    Err(Error::config(format!("Нет такого файла или директории: {}", spec.display())))
+}
+
+/// Видеофайлы директории и ее поддиректорий
+fn walk(directory: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(directory).map_err(|e| Error::file(directory, e))? {
+        let path = entry.map_err(|e| Error::file(directory, e))?.path();
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else if is_video_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn sort_paths(paths: &mut [PathBuf]) {
+    paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+}
+
+/// Одна задача пакета
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchJob {
+    pub input: PathBuf,
+    pub output: PathBuf,
+}
+
+impl BatchJob {
+    /// Задачи для входов: `{выход}/{относительная директория}/{имя}_derivative.{extension}`.
+    /// Повторяющиеся входы и файлы внутри `output_dir` (результаты прошлых
+    /// запусков) пропускаются; два входа с одним выходом - ошибка.
+    pub fn plan(inputs: &[BatchInput], output_dir: impl AsRef<Path>, extension: &str) -> Result<Vec<BatchJob>> {
+        let output_dir = output_dir.as_ref();
+        let output_root = fs::canonicalize(output_dir).ok();
+        let mut seen_inputs = HashSet::new();
+        let mut seen_outputs = HashSet::new();
+        let mut jobs = Vec::new();
+
+        for input in inputs {
+            let canonical = fs::canonicalize(&input.path).unwrap_or_else(|_| input.path.clone());
+            if output_root.as_ref().is_some_and(|root| canonical.starts_with(root)) || !seen_inputs.insert(canonical) {
+                continue;
+            }
+            let stem = input.relative.file_stem().unwrap_or_default().to_string_lossy();
+            let output = output_dir.join(input.relative.with_file_name(format!("{}_derivative.{}", stem, extension)));
+            if !seen_outputs.insert(output.clone()) {
+                return Err(Error::config(format!(
+                    "Несколько входов дают один выход {} (например, {})",
+                    output.display(),
+                    input.path.display()
+                )));
+            }
+            jobs.push(BatchJob { input: input.path.clone(), output });
+        }
+        Ok(jobs)
+    }
+
+    /// Выход уже есть и не старше входа
+    pub fn is_done(&self) -> bool {
+        let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        match (modified(&self.output), modified(&self.input)) {
+            (Some(output), Some(input)) => output >= input,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// Временный файл рядом с выходом; расширение сохраняется, чтобы
+    /// формат выбирался так же, как для готового файла
+    pub fn partial_output(&self) -> PathBuf {
+        let stem = self.output.file_stem().unwrap_or_default().to_string_lossy();
+        match self.output.extension() {
+            Some(extension) => {
+                self.output.with_file_name(format!("{}.partial.{}", stem, extension.to_string_lossy()))
+            }
+            None => self.output.with_file_name(format!("{}.partial", stem)),
+        }
+    }
+}
+
+/// Итог одной задачи
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Done,
+    /// Выход уже был готов
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchOutcome {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub status: BatchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub seconds: f64,
+}
+
+/// Отчет о пакете; `items` в порядке задач, а не завершения
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub total: usize,
+    pub done: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub seconds: f64,
+    pub items: Vec<BatchOutcome>,
+}
+
+impl BatchReport {
+    fn new(items: Vec<BatchOutcome>, elapsed: Duration) -> Self {
+        let count = |status| items.iter().filter(|item| item.status == status).count();
+        Self {
+            total: items.len(),
+            done: count(BatchStatus::Done),
+            skipped: count(BatchStatus::Skipped),
+            failed: count(BatchStatus::Failed),
+            seconds: elapsed.as_secs_f64(),
+            items,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Пул для пакетной обработки
+#[derive(Debug, Clone)]
+pub struct BatchRunner {
+    jobs: usize,
+    skip_done: bool,
+}
+
+impl Default for BatchRunner {
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+impl BatchRunner {
+    /// `jobs` задач одновременно; внутренняя параллельность задач (rayon)
+    /// работает в том же пуле и не увеличивает число потоков
+    pub fn new(jobs: usize) -> Self {
+        Self { jobs: jobs.max(1), skip_done: true }
+    }
+
+    /// Обрабатывать и те задачи, выход которых уже готов
+    pub fn with_overwrite(mut self) -> Self {
+        self.skip_done = false;
+        self
+    }
+
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Выполняет задачи: `process(вход, временный выход)`, после успеха
+    /// временный файл переименовывается. Ошибка одной задачи не
+    /// останавливает остальные. `on_finish` получает число завершенных
+    /// задач и итог очередной.
+    pub fn run<P, F>(&self, jobs: &[BatchJob], process: P, on_finish: F) -> Result<BatchReport>
+    where
+        P: Fn(&Path, &Path) -> Result<()> + Sync,
+        F: Fn(usize, &BatchOutcome) + Sync,
+    {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .thread_name(|index| format!("batch-{}", index))
+            .build()
+            .map_err(|e| Error::config(format!("Не удалось создать пул потоков: {}", e)))?;
+        let started = Instant::now();
+        let finished = AtomicUsize::new(0);
+
+        let items = pool.install(|| {
+            jobs.par_iter()
+                .with_max_len(1)
+                .map(|job| {
+                    let outcome = self.run_job(job, &process);
+                    on_finish(finished.fetch_add(1, Ordering::SeqCst) + 1, &outcome);
+                    outcome
+                })
+                .collect()
+        });
+        Ok(BatchReport::new(items, started.elapsed()))
+    }
+
+    fn run_job<P>(&self, job: &BatchJob, process: &P) -> BatchOutcome
+    where
+        P: Fn(&Path, &Path) -> Result<()>,
+    {
+        let started = Instant::now();
+        let outcome = |status, error| BatchOutcome {
+            input: job.input.clone(),
+            output: job.output.clone(),
+            status,
+            error,
+            seconds: started.elapsed().as_secs_f64(),
+        };
+        if self.skip_done && job.is_done() {
+            return outcome(BatchStatus::Skipped, None);
+        }
+
+        let partial = job.partial_output();
+        let result = job
+            .output
+            .parent()
+            .map_or(Ok(()), |parent| fs::create_dir_all(parent).map_err(|e| Error::file(parent, e)))
+            .and_then(|_| process(&job.input, &partial))
+            .and_then(|_| fs::rename(&partial, &job.output).map_err(|e| Error::file(&job.output, e)));
+        match result {
+            Ok(()) => outcome(BatchStatus::Done, None),
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                outcome(BatchStatus::Failed, Some(e.to_string()))
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Отмена долгих операций. Токен проверяется между кадрами; при отмене
+//! источники и приемники освобождаются обычным `Drop`, который завершает
+//! дочерние процессы FFmpeg и удаляет временные директории.
+
+use crate::error::{Error, Result};
+use crate::types::VideoFrame;
+use crate::video::stream::VideoSource;
+use std::process::{Child, ExitStatus};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Как часто проверять токен, пока ждем завершения FFmpeg
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Флаг отмены, общий для всех клонов: его можно передать в обработку,
+/// а `cancel` вызвать из другого потока (например, из интерфейса)
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// `Error::Cancelled`, если операция отменена
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Источник, проверяющий токен перед каждым кадром
+pub struct CancellableSource<S> {
+    source: S,
+    token: CancellationToken,
+}
+
+impl<S> CancellableSource<S> {
+    pub fn new(source: S, token: CancellationToken) -> Self {
+        Self { source, token }
+    }
+}
+
+impl<S: VideoSource> VideoSource for CancellableSource<S> {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        self.token.check()?;
+        self.source.next_frame()
+    }
+
+    fn frame_count_hint(&self) -> Option<usize> {
+        self.source.frame_count_hint()
+    }
+}
+
+/// Ждет завершения процесса; при отмене завершает его и возвращает
+/// `Error::Cancelled`
+pub(crate) fn wait_cancellable(child: &mut Child, token: &CancellationToken) -> Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if token.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Error::Cancelled);
+        }
+        thread::sleep(CANCEL_POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Видеокодек для выходного файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Vp9,
+    Av1,
+    /// Архивный кодек без потерь
+    Ffv1,
+    ProRes,
+    /// Анимированный WebP (через FFmpeg, `image` не умеет анимацию WebP)
+    WebP,
+}
+
+impl VideoCodec {
+    /// Имя кодировщика FFmpeg
+    pub fn encoder_name(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::H265 => "libx265",
+            VideoCodec::Vp9 => "libvpx-vp9",
+            VideoCodec::Av1 => "libaom-av1",
+            VideoCodec::Ffv1 => "ffv1",
+            VideoCodec::ProRes => "prores_ks",
+            VideoCodec::WebP => "libwebp_anim",
+        }
+    }
+
+    /// Расширение файла по умолчанию для контейнера, поддерживающего кодек
+    pub fn default_extension(self) -> &'static str {
+        match self {
+            VideoCodec::H264 | VideoCodec::H265 | VideoCodec::Av1 => "mp4",
+            VideoCodec::Vp9 => "webm",
+            VideoCodec::Ffv1 => "mkv",
+            VideoCodec::ProRes => "mov",
+            VideoCodec::WebP => "webp",
+        }
+    }
+}
+
+impl FromStr for VideoCodec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "h264" | "avc" | "x264" | "libx264" => Ok(VideoCodec::H264),
+            "h265" | "hevc" | "x265" | "libx265" => Ok(VideoCodec::H265),
+            "vp9" | "libvpx-vp9" => Ok(VideoCodec::Vp9),
+            "av1" | "libaom-av1" => Ok(VideoCodec::Av1),
+            "ffv1" => Ok(VideoCodec::Ffv1),
+            "prores" | "prores_ks" => Ok(VideoCodec::ProRes),
+            "webp" | "libwebp_anim" => Ok(VideoCodec::WebP),
+            other => Err(Error::config(format!(
+                "Неизвестный кодек: {} (доступны h264, h265, vp9, av1, ffv1, prores, webp)",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::H265 => "h265",
+            VideoCodec::Vp9 => "vp9",
+            VideoCodec::Av1 => "av1",
+            VideoCodec::Ffv1 => "ffv1",
+            VideoCodec::ProRes => "prores",
+            VideoCodec::WebP => "webp",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Формат пикселей выходного видео
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderPixelFormat {
+    Yuv420p,
+    Yuv422p,
+    Yuv444p,
+    Yuv420p10,
+    Yuv422p10,
+    Yuv444p10,
+    Gray,
+    Gray16,
+    Rgb24,
+    Bgra,
+    /// Планарный RGB: без потерь для FFV1, x265, VP9 и AV1
+    Gbrp,
+    /// Упакованный RGB для `libx264rgb`
+    Bgr0,
+}
+
+impl EncoderPixelFormat {
+    pub fn as_ffmpeg(self) -> &'static str {
+        match self {
+            EncoderPixelFormat::Yuv420p => "yuv420p",
+            EncoderPixelFormat::Yuv422p => "yuv422p",
+            EncoderPixelFormat::Yuv444p => "yuv444p",
+            EncoderPixelFormat::Yuv420p10 => "yuv420p10le",
+            EncoderPixelFormat::Yuv422p10 => "yuv422p10le",
+            EncoderPixelFormat::Yuv444p10 => "yuv444p10le",
+            EncoderPixelFormat::Gray => "gray",
+            EncoderPixelFormat::Gray16 => "gray16le",
+            EncoderPixelFormat::Rgb24 => "rgb24",
+            EncoderPixelFormat::Bgra => "bgra",
+            EncoderPixelFormat::Gbrp => "gbrp",
+            EncoderPixelFormat::Bgr0 => "bgr0",
+        }
+    }
+
+    /// RGB без перевода в YUV
+    pub fn is_rgb(self) -> bool {
+        matches!(
+            self,
+            EncoderPixelFormat::Rgb24 | EncoderPixelFormat::Bgra | EncoderPixelFormat::Gbrp | EncoderPixelFormat::Bgr0
+        )
+    }
+}
+
+impl FromStr for EncoderPixelFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "yuv420p" => Ok(EncoderPixelFormat::Yuv420p),
+            "yuv422p" => Ok(EncoderPixelFormat::Yuv422p),
+            "yuv444p" => Ok(EncoderPixelFormat::Yuv444p),
+            "yuv420p10" | "yuv420p10le" => Ok(EncoderPixelFormat::Yuv420p10),
+            "yuv422p10" | "yuv422p10le" => Ok(EncoderPixelFormat::Yuv422p10),
+            "yuv444p10" | "yuv444p10le" => Ok(EncoderPixelFormat::Yuv444p10),
+            "gray" | "grey" => Ok(EncoderPixelFormat::Gray),
+            "gray16" | "gray16le" => Ok(EncoderPixelFormat::Gray16),
+            "rgb24" | "rgb" => Ok(EncoderPixelFormat::Rgb24),
+            "bgra" => Ok(EncoderPixelFormat::Bgra),
+            "gbrp" => Ok(EncoderPixelFormat::Gbrp),
+            "bgr0" => Ok(EncoderPixelFormat::Bgr0),
+            other => Err(Error::config(format!("Неизвестный формат пикселей: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for EncoderPixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ffmpeg())
+    }
+}
+
+/// Управление качеством/битрейтом
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateControl {
+    /// Постоянное качество (меньше - лучше). Для WebP переводится в
+    /// `-quality 100 - crf`
+    Crf(u8),
+    /// Целевой битрейт в синтаксисе FFmpeg, например `8M`
+    Bitrate(String),
+    /// Сжатие без потерь
+    Lossless,
+    /// Квантователь ProRes (`-qscale:v`, 1..=31, меньше - лучше)
+    Quality(u8),
+}
+
+/// Настройки кодирования выходного видео
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "EncoderConfigFields")]
+pub struct EncoderConfig {
+    pub codec: VideoCodec,
+    pub rate_control: RateControl,
+    /// Пресет скорости (`medium`, `slow`...) для x264/x265, `-cpu-used` для AV1,
+    /// `-deadline` для VP9 и профиль (`hq`, `4444`...) для ProRes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Формат пикселей; `None` - подходящий для кодека по умолчанию
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pixel_format: Option<EncoderPixelFormat>,
+    /// Явный контейнер (`-f`); иначе FFmpeg выбирает его по расширению
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+}
+
+impl Default for EncoderConfig {
+    fn default() -> Self {
+        Self {
+            codec: VideoCodec::H264,
+            rate_control: RateControl::Crf(23),
+            preset: None,
+            pixel_format: None,
+            container: None,
+        }
+    }
+}
+
+/// Поля `EncoderConfig` в файле конфигурации: без `rate_control`
+/// берется значение по умолчанию для кодека, как в `EncoderConfig::new`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct EncoderConfigFields {
+    codec: VideoCodec,
+    rate_control: Option<RateControl>,
+    preset: Option<String>,
+    pixel_format: Option<EncoderPixelFormat>,
+    container: Option<String>,
+}
+
+impl From<EncoderConfigFields> for EncoderConfig {
+    fn from(fields: EncoderConfigFields) -> Self {
+        let defaults = EncoderConfig::new(fields.codec);
+        Self {
+            codec: fields.codec,
+            rate_control: fields.rate_control.unwrap_or(defaults.rate_control),
+            preset: fields.preset,
+            pixel_format: fields.pixel_format,
+            container: fields.container,
+        }
+    }
+}
+
+impl EncoderConfig {
+    /// Квантователь ProRes по умолчанию: визуально без потерь для профиля `hq`
+    pub const DEFAULT_PRORES_QUALITY: u8 = 9;
+
+    pub fn new(codec: VideoCodec) -> Self {
+        match codec {
+            VideoCodec::Ffv1 => Self::lossless(codec),
+            VideoCodec::ProRes => Self {
+                codec,
+                rate_control: RateControl::Quality(Self::DEFAULT_PRORES_QUALITY),
+                ..Self::default()
+            },
+            _ => Self {
+                codec,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Архивное кодирование без потерь
+    pub fn lossless(codec: VideoCodec) -> Self {
+        Self {
+            codec,
+            rate_control: RateControl::Lossless,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_crf(mut self, crf: u8) -> Self {
+        self.rate_control = RateControl::Crf(crf);
+        self
+    }
+
+    pub fn with_bitrate(mut self, bitrate: &str) -> Self {
+        self.rate_control = RateControl::Bitrate(bitrate.to_string());
+        self
+    }
+
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.rate_control = RateControl::Quality(quality);
+        self
+    }
+
+    pub fn with_lossless(mut self) -> Self {
+        self.rate_control = RateControl::Lossless;
+        self
+    }
+
+    pub fn with_preset(mut self, preset: &str) -> Self {
+        self.preset = Some(preset.to_string());
+        self
+    }
+
+    pub fn with_pixel_format(mut self, pixel_format: EncoderPixelFormat) -> Self {
+        self.pixel_format = Some(pixel_format);
+        self
+    }
+
+    pub fn with_container(mut self, container: &str) -> Self {
+        self.container = Some(container.to_string());
+        self
+    }
+
+    /// Проверяет совместимость кодека и режима сжатия и известность контейнера
+    pub fn validate(&self) -> Result<()> {
+        if let Some(container) = &self.container
+            && muxer_name(container).is_none()
+        {
+            return Err(Error::config(format!(
+                "Неизвестный контейнер: {} (доступны mp4, mkv, mov, webm, avi, nut, ts, webp, y4m)",
+                container
+            )));
+        }
+        match (self.codec, &self.rate_control) {
+            (VideoCodec::ProRes, RateControl::Lossless) => {
+                Err(Error::config("ProRes не поддерживает сжатие без потерь, используйте FFV1"))
+            }
+            (VideoCodec::Ffv1, RateControl::Crf(_) | RateControl::Bitrate(_) | RateControl::Quality(_)) => {
+                Err(Error::config("FFV1 всегда сжимает без потерь, CRF и битрейт для него не задаются"))
+            }
+            (VideoCodec::ProRes, RateControl::Crf(_)) => {
+                Err(Error::config("ProRes не поддерживает CRF, используйте quality (1..=31) или битрейт"))
+            }
+            (VideoCodec::ProRes, RateControl::Quality(quality)) if !(1..=31).contains(quality) => {
+                Err(Error::config(format!("Quality {} вне диапазона 1..=31", quality)))
+            }
+            (codec, RateControl::Quality(_)) if codec != VideoCodec::ProRes => {
+                Err(Error::config(format!("Quality задается только для ProRes, для {} используйте CRF", codec)))
+            }
+            (VideoCodec::H264 | VideoCodec::H265, RateControl::Crf(crf)) if *crf > 51 => {
+                Err(Error::config(format!("CRF {} вне диапазона 0..=51", crf)))
+            }
+            (VideoCodec::Vp9 | VideoCodec::Av1, RateControl::Crf(crf)) if *crf > 63 => {
+                Err(Error::config(format!("CRF {} вне диапазона 0..=63", crf)))
+            }
+            (VideoCodec::WebP, RateControl::Bitrate(_)) => {
+                Err(Error::config("WebP не поддерживает целевой битрейт, используйте CRF"))
+            }
+            (VideoCodec::WebP, RateControl::Crf(crf)) if *crf > 100 => {
+                Err(Error::config(format!("CRF {} вне диапазона 0..=100", crf)))
+            }
+            (_, RateControl::Bitrate(bitrate)) if bitrate.trim().is_empty() => {
+                Err(Error::config("Пустой битрейт"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Формат пикселей с учетом значения по умолчанию для кодека
+    pub fn effective_pixel_format(&self) -> EncoderPixelFormat {
+        if let Some(pixel_format) = self.pixel_format {
+            return pixel_format;
+        }
+        match (self.codec, &self.rate_control) {
+            // Без потерь кадры остаются в RGB: перевод в YUV сам по себе теряет точность
+            // libwebp принимает только yuv420p или RGB с альфой
+            (VideoCodec::WebP, RateControl::Lossless) => EncoderPixelFormat::Bgra,
+            (VideoCodec::WebP, _) => EncoderPixelFormat::Yuv420p,
+            (VideoCodec::H264, RateControl::Lossless) => EncoderPixelFormat::Bgr0,
+            (VideoCodec::Ffv1, _) | (_, RateControl::Lossless) => EncoderPixelFormat::Gbrp,
+            (VideoCodec::ProRes, _) => match self.preset.as_deref() {
+                Some("4444") | Some("4444xq") => EncoderPixelFormat::Yuv444p10,
+                _ => EncoderPixelFormat::Yuv422p10,
+            },
+            _ => EncoderPixelFormat::Yuv420p,
+        }
+    }
+
+    /// Имя кодировщика FFmpeg: x264 принимает RGB только в отдельной сборке `libx264rgb`
+    pub fn encoder_name(&self) -> &'static str {
+        if self.codec == VideoCodec::H264 && self.effective_pixel_format().is_rgb() {
+            "libx264rgb"
+        } else {
+            self.codec.encoder_name()
+        }
+    }
+
+    /// Выходные аргументы FFmpeg для видеопотока
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec!["-c:v".into(), self.encoder_name().into()];
+        let mut push = |items: &[&str]| args.extend(items.iter().map(|s| s.to_string()));
+
+        match (self.codec, &self.rate_control) {
+            (VideoCodec::H264, RateControl::Lossless) => push(&["-qp", "0"]),
+            (VideoCodec::H265, RateControl::Lossless) => push(&["-x265-params", "lossless=1"]),
+            (VideoCodec::Vp9, RateControl::Lossless) => push(&["-lossless", "1"]),
+            (VideoCodec::Av1, RateControl::Lossless) => push(&["-aom-params", "lossless=1"]),
+            (VideoCodec::WebP, RateControl::Lossless) => push(&["-lossless", "1"]),
+            (VideoCodec::WebP, RateControl::Crf(crf)) => {
+                push(&["-quality", &(100 - (*crf).min(100)).to_string()])
+            }
+            (VideoCodec::Ffv1, _) => push(&["-level", "3", "-g", "1"]),
+            (VideoCodec::H264 | VideoCodec::H265, RateControl::Crf(crf)) => {
+                push(&["-crf", &crf.to_string()])
+            }
+            // VP9/AV1 работают в режиме постоянного качества только с -b:v 0
+            (VideoCodec::Vp9 | VideoCodec::Av1, RateControl::Crf(crf)) => {
+                push(&["-crf", &crf.to_string(), "-b:v", "0"])
+            }
+            (VideoCodec::ProRes, RateControl::Quality(quality)) => {
+                push(&["-qscale:v", &quality.to_string()])
+            }
+            (_, RateControl::Bitrate(bitrate)) => push(&["-b:v", bitrate]),
+            // Отклоняются в validate
+            (VideoCodec::ProRes, RateControl::Lossless | RateControl::Crf(_)) | (_, RateControl::Quality(_)) => {}
+        }
+
+        if let Some(preset) = &self.preset {
+            match self.codec {
+                VideoCodec::H264 | VideoCodec::H265 => push(&["-preset", preset]),
+                VideoCodec::Vp9 => push(&["-deadline", preset]),
+                VideoCodec::Av1 => push(&["-cpu-used", preset]),
+                VideoCodec::ProRes => push(&["-profile:v", preset]),
+                // default, picture, photo, drawing, icon, text
+                VideoCodec::WebP => push(&["-preset", preset]),
+                VideoCodec::Ffv1 => {}
+            }
+        }
+
+        push(&["-pix_fmt", self.effective_pixel_format().as_ffmpeg()]);
+        if self.codec == VideoCodec::WebP {
+            // Бесконечный цикл, как у GIF
+            push(&["-loop", "0"]);
+        }
+
+        if let Some(container) = &self.container {
+            // Неизвестный контейнер отклоняется в validate
+            push(&["-f", muxer_name(container).unwrap_or(container)]);
+        }
+        args
+    }
+}
+
+/// Имя мультиплексора FFmpeg для контейнера: Matroska и Y4M называются
+/// не по расширению
+fn muxer_name(container: &str) -> Option<&'static str> {
+    match container.to_ascii_lowercase().as_str() {
+        "mp4" => Some("mp4"),
+        "mkv" | "matroska" => Some("matroska"),
+        "mov" => Some("mov"),
+        "webm" => Some("webm"),
+        "avi" => Some("avi"),
+        "nut" => Some("nut"),
+        "ts" | "mpegts" => Some("mpegts"),
+        "webp" => Some("webp"),
+        "y4m" | "yuv4mpegpipe" => Some("yuv4mpegpipe"),
+        _ => None,
+    }
+}
@@ -1,24 +1,188 @@
+use crate::error::{Error, Result};
 use crate::types::VideoFrame;
-use std::process::{Command, Stdio};
+use crate::video::animated::{ApngSink, GifSink};
+use crate::video::audio::{AudioMode, AudioTrack};
+use crate::video::encoder::{EncoderConfig, VideoCodec};
+use crate::video::frame_rate::FrameRate;
+use crate::video::probe::{probe, MediaInfo, VideoStreamInfo};
+use crate::processors::{CompositeLayout, MotionEvent, MotionEventDetector};
+use crate::types::ProcessingConfig;
+use crate::render::MotionAnnotator;
+use crate::video::stream::{process_stream_with, VideoSink, VideoSource};
+use crate::video::image_sequence::{is_image_sequence_input, is_image_sequence_output, ImageSequenceSink, ImageSequenceSource};
+use crate::video::live::{LiveConfig, LiveFormat};
+use crate::video::cancel::{wait_cancellable, CancellableSource, CancellationToken};
+use crate::video::progress::{ProgressSink, ProgressStage, ProgressTracker, ProgressVideoSink, SilentProgress};
+use crate::video::range::{FrameRange, RangedSource};
+use crate::video::y4m::{Y4mReader, Y4mWriter};
+use crate::video::timestamps::{is_uniform, parse_showinfo_pts, spawn_pts_reader, spawn_stderr_tail, write_ffconcat};
+use image::RgbImage;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::thread::JoinHandle;
+use std::time::Duration;
 use std::fs;
 use tempfile::TempDir;
 
+/// Способ передачи кадров между FFmpeg и процессом
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameTransport {
+    /// Сырые RGB24 кадры через stdin/stdout
+    #[default]
+    Pipe,
+    /// PNG-файлы во временной директории (медленнее, но проще отлаживать)
+    Png,
+}
+
 pub struct VideoProcessor {
-    temp_dir: TempDir,
     ffmpeg_available: bool,
+    transport: FrameTransport,
+    preserve_timestamps: bool,
+    audio_mode: AudioMode,
+    encoder: EncoderConfig,
+    layout: CompositeLayout,
+    annotations: Option<MotionAnnotator>,
+    range: FrameRange,
+    processing: ProcessingConfig,
+    progress: Arc<dyn ProgressSink>,
+    cancellation: CancellationToken,
 }
 
 impl VideoProcessor {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let temp_dir = TempDir::new()?;
+    pub fn new() -> Result<Self> {
         let ffmpeg_available = Self::check_ffmpeg_availability();
 
         Ok(Self {
-            temp_dir,
             ffmpeg_available,
+            transport: FrameTransport::default(),
+            preserve_timestamps: false,
+            audio_mode: AudioMode::default(),
+            encoder: EncoderConfig::default(),
+            layout: CompositeLayout::default(),
+            annotations: None,
+            range: FrameRange::default(),
+            processing: ProcessingConfig::default(),
+            progress: Arc::new(SilentProgress),
+            cancellation: CancellationToken::new(),
         })
     }
 
+    /// Выбирает способ передачи кадров
+    pub fn with_transport(mut self, transport: FrameTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn transport(&self) -> FrameTransport {
+        self.transport
+    }
+
+    /// Сохранять длительности кадров видео с переменной частотой. Сырой pipe
+    /// не передает покадровые метки, поэтому такие кадры кодируются через
+    /// временные PNG - заметно медленнее. По умолчанию VFR-видео кодируется
+    /// с постоянной частотой.
+    pub fn with_preserved_timestamps(mut self, preserve: bool) -> Self {
+        self.preserve_timestamps = preserve;
+        self
+    }
+
+    pub fn preserves_timestamps(&self) -> bool {
+        self.preserve_timestamps
+    }
+
+    /// Переносить ли звук исходника в производное видео
+    pub fn with_audio(mut self, audio_mode: AudioMode) -> Self {
+        self.audio_mode = audio_mode;
+        self
+    }
+
+    pub fn audio_mode(&self) -> &AudioMode {
+        &self.audio_mode
+    }
+
+    /// Настройки кодирования выходного видео
+    pub fn with_encoder(mut self, encoder: EncoderConfig) -> Self {
+        self.encoder = encoder;
+        self
+    }
+
+    pub fn encoder(&self) -> &EncoderConfig {
+        &self.encoder
+    }
+
+    /// Раскладка выходного видео: только производная, рядом с исходником,
+    /// сетка с маской и тепловой картой или подсветка поверх исходника
+    pub fn with_layout(mut self, layout: CompositeLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn layout(&self) -> CompositeLayout {
+        self.layout
+    }
+
+    /// Рисовать области движения, номера треков и траектории. При раскладке
+    /// с исходником аннотации наносятся на исходный кадр.
+    pub fn with_annotations(mut self, annotations: MotionAnnotator) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Обрабатывать только фрагмент видео и/или каждый N-й кадр
+    pub fn with_range(mut self, range: FrameRange) -> Self {
+        self.range = range;
+        self
+    }
+
+    pub fn range(&self) -> &FrameRange {
+        &self.range
+    }
+
+    /// Параметры обработки для `create_derivative_video`: шумоподавление,
+    /// нормировка, геометрия кадров. Частота и порог задаются при вызове.
+    pub fn with_processing(mut self, processing: ProcessingConfig) -> Self {
+        self.processing = processing;
+        self
+    }
+
+    pub fn processing(&self) -> &ProcessingConfig {
+        &self.processing
+    }
+
+    /// Куда отправлять отчеты о ходе обработки; по умолчанию никуда
+    pub fn with_progress<S: ProgressSink + 'static>(mut self, progress: S) -> Self {
+        self.progress = Arc::new(progress);
+        self
+    }
+
+    /// Токен отмены: обработка прерывается между кадрами с `Error::Cancelled`,
+    /// дочерние процессы FFmpeg завершаются, временные файлы удаляются
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    fn tracker(&self, stage: ProgressStage, total: Option<usize>) -> ProgressTracker {
+        ProgressTracker::new(self.progress.clone(), stage, total)
+    }
+
+    /// Настройки кодирования для выходного файла: `.webp` всегда
+    /// кодируется в анимированный WebP, даже если выбран другой кодек
+    fn encoder_for(&self, output_path: &str) -> EncoderConfig {
+        if has_extension(output_path, "webp") && self.encoder.codec != VideoCodec::WebP {
+            EncoderConfig::new(VideoCodec::WebP)
+        } else {
+            self.encoder.clone()
+        }
+    }
+
     fn check_ffmpeg_availability() -> bool {
         Command::new("ffmpeg")
             .arg("-version")
@@ -33,40 +197,791 @@ impl VideoProcessor {
         self.ffmpeg_available
     }
 
-    pub fn extract_frames_from_video(
+    /// Сведения о файле через ffprobe
+    pub fn probe(&self, video_path: &str) -> Result<MediaInfo> {
+        probe(video_path)
+    }
+
+    /// Открывает видео как потоковый источник кадров с учетом `with_range`.
+    /// Y4M и последовательности изображений читаются без FFmpeg; остальное
+    /// FFmpeg декодирует в сырые RGB24 кадры через pipe, а с
+    /// `FrameTransport::Png` - через PNG во временной директории.
+    pub fn open_source(
         &self,
         video_path: &str,
         fps: Option<u32>,
-    ) -> Result<Vec<VideoFrame>, Box<dyn std::error::Error>> {
+    ) -> Result<Box<dyn VideoSource + Send>> {
+        self.open_source_at(video_path, fps.map(FrameRate::from))
+    }
+
+    /// `open_source` с дробной частотой для последовательностей изображений
+    fn open_source_at(
+        &self,
+        video_path: &str,
+        fps: Option<FrameRate>,
+    ) -> Result<Box<dyn VideoSource + Send>> {
+        self.range.validate()?;
+        self.cancellation.check()?;
+        let source: Box<dyn VideoSource + Send> = if is_y4m(video_path) {
+            // Y4M читается без FFmpeg с частотой из заголовка
+            Box::new(RangedSource::new(Y4mReader::open(video_path)?, self.range.clone()))
+        } else if is_image_sequence_input(video_path) {
+            // У последовательности изображений нет своей частоты, ее задают явно
+            let fps = fps.ok_or_else(|| Error::config("Для последовательности изображений нужно указать частоту кадров"))?;
+            let sequence = ImageSequenceSource::open(video_path, fps.as_f64())?;
+            Box::new(RangedSource::new(sequence, self.range.clone()))
+        } else if !self.ffmpeg_available {
+            return Err(Error::FfmpegNotFound);
+        } else {
+            // Для видео частота задает только метки времени вместо PTS
+            let fps = fps.map(FrameRate::rounded);
+            match self.transport {
+                FrameTransport::Pipe => Box::new(FfmpegFrameSource::open_range(video_path, fps, &self.range)?),
+                FrameTransport::Png => Box::new(PngFrameSource::open_cancellable(
+                    video_path,
+                    fps,
+                    &self.range,
+                    &self.cancellation,
+                )?),
+            }
+        };
+        Ok(Box::new(CancellableSource::new(source, self.cancellation.clone())))
+    }
+
+    /// Создает потоковый приемник, кодирующий кадры в видеофайл
+    pub fn create_sink(
+        &self,
+        output_path: &str,
+        fps: impl Into<FrameRate>,
+    ) -> Result<Box<dyn VideoSink + Send>> {
+        let fps = fps.into();
+        if is_y4m(output_path) {
+            return Ok(Box::new(Y4mWriter::create(output_path, fps.rounded())?.with_frame_rate(fps.num, fps.den)));
+        }
+        if is_image_sequence_output(output_path) {
+            return Ok(Box::new(ImageSequenceSink::create(output_path)?));
+        }
+        if has_extension(output_path, "gif") {
+            return Ok(Box::new(GifSink::create(output_path, fps.rounded())?));
+        }
+        if has_extension(output_path, "apng") {
+            return Ok(Box::new(ApngSink::create(output_path, fps.rounded())?));
+        }
+        if !self.ffmpeg_available {
+            return Err(Error::FfmpegNotFound);
+        }
+        let encoder = self.encoder_for(output_path);
+        encoder.validate()?;
+        Ok(match self.transport {
+            FrameTransport::Pipe => Box::new(FfmpegVideoSink::create(output_path, fps)?.with_encoder(encoder)),
+            FrameTransport::Png => Box::new(PngVideoSink::create(output_path, fps)?.with_encoder(encoder)),
+        })
+    }
+
+    /// Создает приемник, сохраняющий метки времени кадров (для VFR-видео).
+    /// Через сырой pipe FFmpeg не принимает покадровые метки, поэтому кадры
+    /// пишутся во временные файлы и собираются concat-демультиплексором.
+    pub fn create_vfr_sink(
+        &self,
+        output_path: &str,
+        fps: impl Into<FrameRate>,
+    ) -> Result<Box<dyn VideoSink + Send>> {
+        let fps = fps.into();
+        // Y4M и последовательности изображений не хранят длительности кадров,
+        // а GIF и APNG сами берут их из меток времени
+        if is_native_output(output_path) {
+            return self.create_sink(output_path, fps);
+        }
         if !self.ffmpeg_available {
-            return Err("FFmpeg не доступен. Установите FFmpeg для извлечения кадров из видео.".into());
+            return Err(Error::FfmpegNotFound);
         }
+        let encoder = self.encoder_for(output_path);
+        encoder.validate()?;
+        Ok(Box::new(
+            PngVideoSink::create(output_path, fps)?
+                .preserving_timestamps()
+                .with_encoder(encoder)
+                .with_cancellation(self.cancellation.clone()),
+        ))
+    }
 
-        let frames_dir = self.temp_dir.path().join("extracted_frames");
-        fs::create_dir_all(&frames_dir)?;
+    /// Создает приемник со звуковой дорожкой из исходного файла.
+    /// Звук выравнивается по метке времени первого записанного кадра.
+    pub fn create_sink_with_audio(
+        &self,
+        output_path: &str,
+        fps: impl Into<FrameRate>,
+        preserve_timestamps: bool,
+        audio: AudioTrack,
+    ) -> Result<Box<dyn VideoSink + Send>> {
+        let fps = fps.into();
+        let silent_format = is_native_output(output_path) || has_extension(output_path, "webp");
+        if silent_format && audio.is_enabled() {
+            return Err(Error::config(format!("{} не может содержать звук", output_path)));
+        }
+        if is_native_output(output_path) {
+            return self.create_sink(output_path, fps);
+        }
+        if !self.ffmpeg_available {
+            return Err(Error::FfmpegNotFound);
+        }
+        let encoder = self.encoder_for(output_path);
+        encoder.validate()?;
+        Ok(match (preserve_timestamps, self.transport) {
+            (false, FrameTransport::Pipe) => Box::new(
+                FfmpegVideoSink::create(output_path, fps)?.with_audio(audio).with_encoder(encoder),
+            ),
+            (false, FrameTransport::Png) => Box::new(
+                PngVideoSink::create(output_path, fps)?
+                    .with_audio(audio)
+                    .with_encoder(encoder)
+                    .with_cancellation(self.cancellation.clone()),
+            ),
+            (true, _) => Box::new(
+                PngVideoSink::create(output_path, fps)?
+                    .preserving_timestamps()
+                    .with_audio(audio)
+                    .with_encoder(encoder)
+                    .with_cancellation(self.cancellation.clone()),
+            ),
+        })
+    }
+
+    /// Извлекает кадры в память. Фрагмент и шаг задаются через `with_range`,
+    /// так из двухчасовой записи декодируется только нужное окно.
+    pub fn extract_frames_from_video(
+        &self,
+        video_path: &str,
+        fps: Option<u32>,
+    ) -> Result<Vec<VideoFrame>> {
+        let source = self.open_source(video_path, fps)?;
+        let mut tracker = self.tracker(ProgressStage::Decoding, source.frame_count_hint());
+        let frames = source
+            .frames()
+            .inspect(|_| tracker.advance())
+            .collect::<Result<Vec<_>>>()?;
+
+        if frames.is_empty() {
+            return Err(Error::NoFrames(video_path.to_string()));
+        }
+        Ok(frames)
+    }
+
+    pub fn save_frames_to_video(
+        &self,
+        frames: &[VideoFrame],
+        output_path: &str,
+        fps: impl Into<FrameRate>,
+    ) -> Result<()> {
+        let fps = fps.into();
+        let timestamps: Vec<f64> = frames.iter().map(|f| f.timestamp).collect();
+        let mut sink = if !self.preserve_timestamps || is_uniform(&timestamps) {
+            self.create_sink(output_path, fps)?
+        } else {
+            self.create_vfr_sink(output_path, fps)?
+        };
+        let mut tracker = self.tracker(ProgressStage::Encoding, Some(frames.len()));
+        let result = frames
+            .iter()
+            .try_for_each(|frame| {
+                self.cancellation.check()?;
+                sink.write_frame(frame)?;
+                tracker.advance();
+                Ok(())
+            })
+            .and_then(|()| sink.finish());
+        drop(sink);
+        discard_if_cancelled(result, output_path)
+
+    }
+
+    pub fn create_simple_derivative_video(
+        &self,
+        input_path: &str,
+        output_path: &str,
+    ) -> Result<()> {
+        if is_image_sequence_input(input_path) {
+            return Err(Error::config("Для последовательности изображений используйте create_derivative_video с явной частотой кадров"));
+        }
+        let native = is_y4m(input_path) && is_native_output(output_path);
+        if !self.ffmpeg_available && !native {
+            return Err(Error::FfmpegNotFound);
+        }
+
+        let fps = self.detect_frame_rate(input_path)?;
+        // Используем наш Rust-процессор вместо FFmpeg фильтра
+        self.create_derivative_video(input_path, output_path, fps, 25)
+    }
+
+    /// Частота кадров входа без округления: из заголовка Y4M или через
+    /// ffprobe (30, если поток ее не сообщает). У последовательностей
+    /// изображений частоты нет.
+    pub fn detect_frame_rate(&self, input_path: &str) -> Result<FrameRate> {
+        if is_image_sequence_input(input_path) {
+            return Err(Error::config("У последовательности изображений нет своей частоты кадров, укажите ее явно"));
+        }
+        let rate = if is_y4m(input_path) {
+            let (num, den) = Y4mReader::open(input_path)?.header().frame_rate;
+            Some(FrameRate::new(num, den))
+        } else {
+            probe_video_stream(input_path)?.effective_frame_rate().and_then(FrameRate::from_f64)
+        };
+        Ok(rate.unwrap_or(FrameRate::from(30)))
+    }
+
+    /// Считает производные без записи видео и передает каждую в `inspect`
+    /// (анализ, поиск событий). Учитывает `with_range`, `with_processing`,
+    /// отчеты о ходе и отмену. Возвращает количество кадров.
+    pub fn scan_derivatives<F>(
+        &self,
+        input_path: &str,
+        fps: Option<u32>,
+        threshold: u8,
+        mut inspect: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&VideoFrame) -> Result<()>,
+    {
+        use crate::processors::VideoDerivativeProcessor;
+
+        let mut source = self.open_source(input_path, fps)?;
+        let mut tracker = self.tracker(ProgressStage::Processing, source.frame_count_hint());
+        let mut processor = VideoDerivativeProcessor::new(ProcessingConfig {
+            fps: fps.unwrap_or(self.processing.fps),
+            threshold,
+            ..self.processing.clone()
+        });
+
+        let mut count = 0;
+        while let Some(frame) = source.next_frame()? {
+            inspect(&processor.process_frame(&frame))?;
+            tracker.advance();
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Строит производное видео потоково: кадры читаются, обрабатываются и
+    /// кодируются по одному, без накопления всего ролика в памяти
+    pub fn create_derivative_video(
+        &self,
+        input_video_path: &str,
+        output_video_path: &str,
+        fps: impl Into<FrameRate>,
+        threshold: u8,
+    ) -> Result<()> {
+        use crate::processors::VideoDerivativeProcessor;
+
+        // Метки времени берутся из PTS декодера; последовательности
+        // изображений получают их из заданной частоты
+        let fps = fps.into();
+        let sequence = is_image_sequence_input(input_video_path);
+        let mut source = self.open_source_at(input_video_path, sequence.then_some(fps))?;
+        let total = source.frame_count_hint();
+        let variable_rate = self.preserve_timestamps
+            && !sequence
+            && !is_y4m(input_video_path)
+            && probe_video_stream(input_video_path)
+                .map(|info| info.is_variable_frame_rate())
+                .unwrap_or(false);
+
+        // При прореживании кадры идут реже, и воспроизведение остается в
+        // реальном времени, чтобы совпадать со звуком: 25 к/с с шагом 3 -
+        // это 25/3, а не 8 к/с
+        let output_fps = fps.divided_by(self.range.step);
+        let config = ProcessingConfig {
+            fps: output_fps.rounded(),
+            threshold,
+            ..self.processing.clone()
+        };
+
+        let mut processor = VideoDerivativeProcessor::new(config);
+        let audio = AudioTrack::new(input_video_path, self.audio_mode.clone());
+        let inner = self.create_sink_with_audio(output_video_path, output_fps, variable_rate, audio)?;
+        let mut sink = ProgressVideoSink {
+            inner,
+            tracker: self.tracker(ProgressStage::Processing, total),
+        };
+
+        let result = process_stream_with(&mut source, &mut processor, &mut sink, self.composer());
+        // Кодировщик и декодер завершаются до удаления недописанного файла
+        drop(sink);
+        drop(source);
+        if discard_if_cancelled(result, output_video_path)? == 0 {
+            return Err(Error::NoFrames(input_video_path.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Обрабатывает живой поток (stdin, FIFO, дописываемый файл): каждая
+    /// производная сразу уходит в приемник, а события движения - в
+    /// `on_event`. Выход `-` - Y4M в stdout для передачи следующей программе.
+    /// Возвращает количество обработанных кадров.
+    pub fn create_live_derivative_video<F>(
+        &self,
+        live: &LiveConfig,
+        output_path: &str,
+        threshold: u8,
+        mut detector: MotionEventDetector,
+        mut on_event: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&MotionEvent) -> Result<()>,
+    {
+        use crate::processors::VideoDerivativeProcessor;
+
+        let uses_ffmpeg = matches!(live.format, LiveFormat::Ffmpeg);
+        if uses_ffmpeg && !self.ffmpeg_available {
+            return Err(Error::FfmpegNotFound);
+        }
+        self.range.validate()?;
+        let source = live.open()?;
+        let fps = FrameRate::from_f64(source.frame_rate()).unwrap_or(FrameRate::from(30));
+        let mut source = RangedSource::new(source, self.range.clone());
+
+        let mut sink: Box<dyn VideoSink> = if output_path == "-" {
+            Box::new(Y4mWriter::new(std::io::stdout(), fps.rounded()).with_frame_rate(fps.num, fps.den))
+        } else {
+            self.create_sink(output_path, fps)?
+        };
+
+        let config = ProcessingConfig {
+            fps: fps.rounded(),
+            threshold,
+            ..self.processing.clone()
+        };
+        let mut processor = VideoDerivativeProcessor::new(config);
+        let mut compose = self.composer();
+        let mut tracker = self.tracker(ProgressStage::Processing, None);
+
+        let mut count = 0;
+        while let Some(frame) = source.next_frame()? {
+            self.cancellation.check()?;
+            let (original, derivative) = processor.process_frame_with_original(frame);
+            if let Some(event) = detector.update(&derivative) {
+                on_event(&event)?;
+            }
+            sink.write_frame(&compose(&original, derivative))?;
+            tracker.advance();
+            count += 1;
+        }
+        if let Some(event) = detector.finish() {
+            on_event(&event)?;
+        }
+        sink.finish()?;
+        Ok(count)
+    }
+
+    /// Сборка выходного кадра: раскладка и аннотации
+    fn composer(&self) -> impl FnMut(&VideoFrame, VideoFrame) -> VideoFrame + use<> {
+        let layout = self.layout;
+        let mut annotations = self.annotations.clone();
+        move |original, mut derivative| match annotations.as_mut() {
+            None => layout.compose(original, derivative),
+            Some(annotations) if layout == CompositeLayout::DerivativeOnly => {
+                annotations.process_in_place(&mut derivative);
+                derivative
+            }
+            Some(annotations) => {
+                let mut annotated = original.clone();
+                annotations.process(&mut annotated, &derivative);
+                layout.compose(&annotated, derivative)
+            }
+        }
+    }
+}
+
+/// При отмене удаляет недописанный выходной файл; директории с
+/// последовательностями изображений не трогает
+fn discard_if_cancelled<T>(result: Result<T>, output_path: &str) -> Result<T> {
+    if matches!(result, Err(Error::Cancelled)) && Path::new(output_path).is_file() {
+        let _ = fs::remove_file(output_path);
+    }
+    result
+}
+
+/// Сравнивает расширение пути без учета регистра
+fn has_extension(path: &str, extension: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Файлы `.y4m` читаются и пишутся без FFmpeg
+pub fn is_y4m(path: &str) -> bool {
+    has_extension(path, "y4m")
+}
+
+/// Выходы, которые пишутся без FFmpeg: Y4M, изображения, GIF и APNG
+pub fn is_native_output(path: &str) -> bool {
+    is_y4m(path)
+        || is_image_sequence_output(path)
+        || has_extension(path, "gif")
+        || has_extension(path, "apng")
+}
+
+/// Сведения о видеопотоке, нужные источникам кадров
+fn probe_video_stream(video_path: &str) -> Result<VideoStreamInfo> {
+    probe(video_path)?
+        .video
+        .ok_or_else(|| Error::decode(format!("В файле {} нет видеопотока", video_path)))
+}
+
+/// Частота для меток времени, если PTS недоступны: явно заданная, из ffprobe или 30
+fn timestamp_rate(fps: Option<u32>, stream: Option<&VideoStreamInfo>) -> f64 {
+    fps.map(|fps| fps as f64)
+        .or_else(|| stream.and_then(|s| s.effective_frame_rate()))
+        .unwrap_or(30.0)
+}
+
+/// Читает ровно один кадр; `false` означает конец потока перед началом кадра
+pub(crate) fn read_raw_frame<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(Error::decode("Поток оборвался посреди кадра")),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+/// Источник кадров из видеофайла: FFmpeg декодирует видео в сырые RGB24
+/// кадры и отдает их через stdout, без промежуточных файлов.
+/// Метки времени берутся из PTS декодера (фильтр `showinfo`), поэтому
+/// VFR-видео сохраняет реальную временную шкалу.
+pub struct FfmpegFrameSource {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+    pts: Receiver<(usize, f64)>,
+    stderr: Option<JoinHandle<Vec<String>>>,
+    info: VideoStreamInfo,
+    width: u32,
+    height: u32,
+    frame_rate: f64,
+    /// Брать метки из PTS; сбрасывается при заданном `fps` и после первого
+    /// кадра, для которого строка `showinfo` не пришла
+    use_pts: bool,
+    range: FrameRange,
+    index: usize,
+    finished: bool,
+}
+
+impl FfmpegFrameSource {
+    /// Открывает видео. Если `fps` задан, метки времени считаются как
+    /// `i / fps`, иначе берутся из PTS декодера.
+    pub fn open(video_path: &str, fps: Option<u32>) -> Result<Self> {
+        Self::open_range(video_path, fps, &FrameRange::default())
+    }
+
+    /// Открывает только фрагмент видео. Метки времени остаются на шкале
+    /// исходника: первый кадр фрагмента с `start = 90` получает метку около 90.
+    pub fn open_range(video_path: &str, fps: Option<u32>, range: &FrameRange) -> Result<Self> {
+        range.validate()?;
+        let info = probe_video_stream(video_path)?;
+        // FFmpeg применяет поворот при декодировании, поэтому размер уже повернутый
+        let (width, height) = info.display_dimensions();
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostats", "-loglevel", "info"])
+            .args(range.input_args())
+            .args(["-i", video_path])
+            // passthrough: без дублирования и выбрасывания кадров под постоянную частоту
+            .args(["-fps_mode", "passthrough", "-vf", &range.filter()])
+            .args(range.output_args())
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn().map_err(Error::spawn)?;
+
+        let stdout = child.stdout.take().ok_or_else(|| Error::decode("Не удалось открыть stdout FFmpeg"))?;
+        let stderr = child.stderr.take().ok_or_else(|| Error::decode("Не удалось открыть stderr FFmpeg"))?;
+        let (pts, stderr) = spawn_pts_reader(stderr);
+
+        Ok(Self {
+            child,
+            stdout: BufReader::new(stdout),
+            pts,
+            stderr: Some(stderr),
+            frame_rate: timestamp_rate(fps, Some(&info)),
+            use_pts: fps.is_none(),
+            range: range.clone(),
+            info,
+            width,
+            height,
+            index: 0,
+            finished: false,
+        })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Сведения ffprobe о декодируемом потоке
+    pub fn stream_info(&self) -> &VideoStreamInfo {
+        &self.info
+    }
+
+    /// PTS кадра с номером `index` на выходе фильтра. Строки сопоставляются
+    /// по номеру `n`, поэтому пропущенная строка не сдвигает метки следующих
+    /// кадров; после первого пропуска метки считаются по частоте до конца
+    /// потока, чтобы не ждать таймаут на каждом кадре.
+    fn frame_pts(&mut self, index: usize) -> Option<f64> {
+        if !self.use_pts {
+            return None;
+        }
+        loop {
+            match self.pts.recv_timeout(Duration::from_secs(2)) {
+                Ok((n, pts)) if n == index => return Some(pts),
+                Ok((n, _)) if n < index => continue,
+                _ => {
+                    self.use_pts = false;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl VideoSource for FfmpegFrameSource {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut buf = vec![0u8; self.width as usize * self.height as usize * 3];
+        if !read_raw_frame(&mut self.stdout, &mut buf)? {
+            self.finished = true;
+            let status = self.child.wait()?;
+            let stderr = self.stderr.take().and_then(|h| h.join().ok()).unwrap_or_default();
+            if !status.success() {
+                return Err(Error::ffmpeg(status, stderr.join("\n")));
+            }
+            return Ok(None);
+        }
+
+        let i = self.index;
+        self.index += 1;
+        let data = RgbImage::from_raw(self.width, self.height, buf)
+            .ok_or_else(|| Error::decode("Размер кадра не совпадает с ожидаемым"))?;
+
+        // showinfo пишет строку до того, как кадр попадает в stdout,
+        // поэтому ожидание короткое; при сбое разбора считаем по частоте.
+        // После входного `-ss` PTS отсчитываются от начала фрагмента.
+        let offset = self
+            .frame_pts(i)
+            .unwrap_or_else(|| (i * self.range.step) as f64 / self.frame_rate);
+        Ok(Some(VideoFrame::new(data, i + 1, self.range.start_seconds() + offset)))
+    }
+
+    fn frame_count_hint(&self) -> Option<usize> {
+        let total = self.info.frame_count.map(|count| count as usize);
+        let rate = self.info.effective_frame_rate().unwrap_or(self.frame_rate);
+        self.range.frame_count_hint(total, rate)
+    }
+}
+
+impl Drop for FfmpegFrameSource {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Приемник, кодирующий кадры в видео через FFmpeg. Сырые RGB24 кадры
+/// передаются в stdin; процесс запускается при первом кадре, когда
+/// становится известен размер.
+pub struct FfmpegVideoSink {
+    output_path: String,
+    fps: FrameRate,
+    audio: Option<AudioTrack>,
+    settings: EncoderConfig,
+    encoder: Option<(Child, BufWriter<ChildStdin>, JoinHandle<Vec<String>>)>,
+    dimensions: Option<(u32, u32)>,
+}
+
+impl FfmpegVideoSink {
+    pub fn create(output_path: &str, fps: impl Into<FrameRate>) -> Result<Self> {
+        Ok(Self {
+            output_path: output_path.to_string(),
+            fps: fps.into(),
+            audio: None,
+            settings: EncoderConfig::default(),
+            encoder: None,
+            dimensions: None,
+        })
+    }
+
+    /// Добавляет звуковую дорожку из исходного файла
+    pub fn with_audio(mut self, audio: AudioTrack) -> Self {
+        self.audio = audio.is_enabled().then_some(audio);
+        self
+    }
+
+    /// Задает кодек и параметры сжатия
+    pub fn with_encoder(mut self, settings: EncoderConfig) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    fn spawn_encoder(&self, width: u32, height: u32, first_timestamp: f64) -> Result<(Child, BufWriter<ChildStdin>, JoinHandle<Vec<String>>)> {
+        let mut command = Command::new("ffmpeg");
+        command.args([
+            "-y",
+            "-v", "error",
+            "-f", "rawvideo",
+            "-pix_fmt", "rgb24",
+            "-s", &format!("{}x{}", width, height),
+            "-framerate", &self.fps.to_string(),
+            "-i", "-",
+        ]);
+        if let Some(audio) = &self.audio {
+            command.args(audio.input_args(first_timestamp));
+            command.args(audio.output_args(1));
+        }
+
+        let mut child = command
+            .args(self.settings.to_args())
+            .arg(&self.output_path)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn().map_err(Error::spawn)?;
+
+        let stdin = child.stdin.take().ok_or_else(|| Error::encode("Не удалось открыть stdin FFmpeg"))?;
+        let stderr = child.stderr.take().ok_or_else(|| Error::encode("Не удалось открыть stderr FFmpeg"))?;
+        Ok((child, BufWriter::new(stdin), spawn_stderr_tail(stderr)))
+    }
+}
+
+impl VideoSink for FfmpegVideoSink {
+    fn write_frame(&mut self, frame: &VideoFrame) -> Result<()> {
+        match self.dimensions {
+            None => {
+                self.encoder = Some(self.spawn_encoder(frame.width(), frame.height(), frame.timestamp)?);
+                self.dimensions = Some(frame.dimensions());
+            }
+            Some(dimensions) if dimensions != frame.dimensions() => {
+                return Err(Error::DimensionMismatch {
+                    expected: dimensions,
+                    actual: frame.dimensions(),
+                });
+            }
+            Some(_) => {}
+        }
+
+        let (_, stdin, _) = self.encoder.as_mut().ok_or_else(|| Error::encode("Кодирование уже завершено"))?;
+        stdin.write_all(frame.data.as_raw())?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let Some((mut child, mut stdin, stderr)) = self.encoder.take() else {
+            return Ok(());
+        };
+
+        stdin.flush()?;
+        // Закрываем stdin, чтобы FFmpeg дописал файл
+        drop(stdin);
+
+        let status = child.wait()?;
+        let stderr = stderr.join().unwrap_or_default();
+        if !status.success() {
+            return Err(Error::ffmpeg(status, stderr.join("\n")));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FfmpegVideoSink {
+    fn drop(&mut self) {
+        if let Some((mut child, stdin, _)) = self.encoder.take() {
+            drop(stdin);
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Запасной источник кадров: FFmpeg раскладывает видео на PNG во
+/// временной директории, а кадры загружаются в память по одному.
+pub struct PngFrameSource {
+    _temp_dir: TempDir,
+    files: std::vec::IntoIter<PathBuf>,
+    timestamps: Vec<f64>,
+    total: usize,
+    frame_rate: f64,
+    range: FrameRange,
+    index: usize,
+}
+
+impl PngFrameSource {
+    pub fn open(video_path: &str, fps: Option<u32>) -> Result<Self> {
+        Self::open_range(video_path, fps, &FrameRange::default())
+    }
+
+    /// Раскладывает на PNG только фрагмент видео (см. `FfmpegFrameSource::open_range`)
+    pub fn open_range(video_path: &str, fps: Option<u32>, range: &FrameRange) -> Result<Self> {
+        Self::open_cancellable(video_path, fps, range, &CancellationToken::new())
+    }
+
+    /// Как `open_range`, но раскладку на PNG можно прервать токеном: FFmpeg
+    /// завершается, временная директория удаляется
+    pub fn open_cancellable(
+        video_path: &str,
+        fps: Option<u32>,
+        range: &FrameRange,
+        cancel: &CancellationToken,
+    ) -> Result<Self> {
+        range.validate()?;
+        let temp_dir = TempDir::new()?;
+        let frames_dir = temp_dir.path();
 
         let output_pattern = frames_dir.join("frame_%06d.png").to_string_lossy().to_string();
 
-        // Упрощенная команда FFmpeg без конфликтующих аргументов
-        let status = Command::new("ffmpeg")
+        // Упрощенная команда FFmpeg без конфликтующих аргументов;
+        // showinfo печатает PTS каждого кадра в stderr
+        let mut child = Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostats", "-loglevel", "info"])
+            .args(range.input_args())
             .arg("-i")
             .arg(video_path)
+            .args(["-fps_mode", "passthrough", "-vf", &range.filter()])
+            .args(range.output_args())
             .arg("-qscale:v")  // Качество изображения
             .arg("2")          // Высокое качество
             .arg(&output_pattern)
-            .status()?;
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn().map_err(Error::spawn)?;
 
+        // stderr читается в отдельном потоке, чтобы FFmpeg не встал на полном канале
+        let mut stderr_pipe = child.stderr.take().ok_or_else(|| Error::decode("Не удалось открыть stderr FFmpeg"))?;
+        let stderr_reader = std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr_pipe.read_to_string(&mut text);
+            text
+        });
+        let status = wait_cancellable(&mut child, cancel)?;
+        let stderr = stderr_reader.join().unwrap_or_default();
         if !status.success() {
-            return Err("Ошибка при извлечении кадров из видео".into());
+            return Err(Error::ffmpeg(status, stderr.trim()));
         }
-
-        // Даем время на запись файлов
-        std::thread::sleep(std::time::Duration::from_secs(2));
-
-        let mut frames = Vec::new();
+        let timestamps = if fps.is_some() {
+            Vec::new()
+        } else {
+            stderr.lines().filter_map(parse_showinfo_pts).collect()
+        };
 
         // Ищем все PNG файлы в директории
-        let entries: Result<Vec<_>, _> = fs::read_dir(&frames_dir)?.collect();
+        let entries: std::io::Result<Vec<_>> = fs::read_dir(frames_dir)?.collect();
         let entries = entries?;
 
         let mut png_files: Vec<_> = entries
@@ -79,131 +994,164 @@ impl VideoProcessor {
                     false
                 }
             })
+            .map(|entry| entry.path())
             .collect();
 
         // Сортируем файлы по имени
-        png_files.sort_by_key(|e| e.path());
-
-        for (i, entry) in png_files.into_iter().enumerate() {
-            match image::open(entry.path()) {
-                Ok(img) => {
-                    let timestamp = i as f64 / fps.unwrap_or(30) as f64;
-                    frames.push(VideoFrame::new(img.to_rgb8(), i + 1, timestamp));
-                }
-                Err(e) => {
-                    eprintln!("Ошибка загрузки кадра {}: {}", entry.path().display(), e);
-                }
-            }
-        }
-
-        if frames.is_empty() {
-            return Err("Не удалось извлечь ни одного кадра из видео".into());
-        }
+        png_files.sort();
 
-        println!("Извлечено {} кадров", frames.len());
-        Ok(frames)
+        Ok(Self {
+            _temp_dir: temp_dir,
+            total: png_files.len(),
+            files: png_files.into_iter(),
+            timestamps,
+            frame_rate: timestamp_rate(fps, probe_video_stream(video_path).ok().as_ref()),
+            range: range.clone(),
+            index: 0,
+        })
     }
+}
 
-    pub fn save_frames_to_video(
-        &self,
-        frames: &[VideoFrame],
-        output_path: &str,
-        fps: u32,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        if !self.ffmpeg_available {
-            return Err("FFmpeg не доступен".into());
-        }
-
-        let frames_dir = self.temp_dir.path().join("output_frames");
-        fs::create_dir_all(&frames_dir)?;
+impl VideoSource for PngFrameSource {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        let Some(path) = self.files.next() else {
+            return Ok(None);
+        };
+        let i = self.index;
+        self.index += 1;
 
-        // Сохраняем все кадры как PNG
-        for (i, frame) in frames.iter().enumerate() {
-            let frame_path = frames_dir.join(format!("frame_{:06}.png", i + 1));
-            frame.save_to_file(&frame_path)?;
-        }
+        // Пропуск кадра сдвинул бы номера и метки времени остальных кадров
+        let img = image::open(&path)
+            .map_err(|e| Error::decode(format!("Ошибка загрузки кадра {}: {}", path.display(), e)))?;
+        // Файл больше не нужен - освобождаем место на диске
+        let _ = fs::remove_file(&path);
+        let offset = self
+            .timestamps
+            .get(i)
+            .copied()
+            .unwrap_or((i * self.range.step) as f64 / self.frame_rate);
+        let timestamp = self.range.start_seconds() + offset;
+        Ok(Some(VideoFrame::new(img.to_rgb8(), i + 1, timestamp)))
+    }
 
-        // Создаем видео с помощью ffmpeg
-        let frame_pattern = frames_dir.join("frame_%06d.png").to_string_lossy().to_string();
-
-        let status = Command::new("ffmpeg")
-            .args([
-                "-y",
-                "-framerate", &fps.to_string(),
-                "-i", &frame_pattern,
-                "-c:v", "libx264",
-                "-pix_fmt", "yuv420p",
-                "-crf", "23",
-                output_path,
-            ])
-            .status()?;
+    fn frame_count_hint(&self) -> Option<usize> {
+        Some(self.total)
+    }
+}
 
-        if !status.success() {
-            return Err("Ошибка при создании видео".into());
-        }
+/// Запасной приемник: кадры сразу сохраняются как PNG во временную
+/// директорию, кодирование FFmpeg запускается в `finish`.
+pub struct PngVideoSink {
+    temp_dir: TempDir,
+    output_path: String,
+    fps: FrameRate,
+    files: Vec<PathBuf>,
+    timestamps: Vec<f64>,
+    preserve_timestamps: bool,
+    audio: Option<AudioTrack>,
+    encoder: EncoderConfig,
+    cancellation: CancellationToken,
+    finished: bool,
+}
 
-        println!("Видео успешно создано: {}", output_path);
-        Ok(())
+impl PngVideoSink {
+    pub fn create(output_path: &str, fps: impl Into<FrameRate>) -> Result<Self> {
+        Ok(Self {
+            temp_dir: TempDir::new()?,
+            output_path: output_path.to_string(),
+            fps: fps.into(),
+            files: Vec::new(),
+            timestamps: Vec::new(),
+            preserve_timestamps: false,
+            audio: None,
+            encoder: EncoderConfig::default(),
+            cancellation: CancellationToken::new(),
+            finished: false,
+        })
     }
 
-    pub fn create_simple_derivative_video(
-        &self,
-        input_path: &str,
-        output_path: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        if !self.ffmpeg_available {
-            return Err("FFmpeg не доступен".into());
-        }
-
-        println!("Обработка видео: {} -> {}", input_path, output_path);
+    /// Добавляет звуковую дорожку из исходного файла
+    pub fn with_audio(mut self, audio: AudioTrack) -> Self {
+        self.audio = audio.is_enabled().then_some(audio);
+        self
+    }
 
-        // Используем наш Rust-процессор вместо FFmpeg фильтра
-        self.create_derivative_video(input_path, output_path, 30, 25)
+    /// Задает кодек и параметры сжатия
+    pub fn with_encoder(mut self, encoder: EncoderConfig) -> Self {
+        self.encoder = encoder;
+        self
     }
 
-    pub fn create_derivative_video(
-        &self,
-        input_video_path: &str,
-        output_video_path: &str,
-        fps: u32,
-        threshold: u8,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        use crate::processors::VideoDerivativeProcessor;
-        use crate::types::ProcessingConfig;
+    /// Токен, прерывающий кодирование в `finish`
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
 
-        println!("Извлечение кадров из видео...");
+    /// Кодировать с длительностями кадров из их меток времени (VFR),
+    /// а не с постоянной частотой `fps`
+    pub fn preserving_timestamps(mut self) -> Self {
+        self.preserve_timestamps = true;
+        self
+    }
+}
 
-        // Извлекаем кадры
-        let frames = self.extract_frames_from_video(input_video_path, Some(fps))?;
+impl VideoSink for PngVideoSink {
+    fn write_frame(&mut self, frame: &VideoFrame) -> Result<()> {
+        let frame_path = self.temp_dir.path().join(format!("frame_{:06}.png", self.files.len() + 1));
+        frame.save_to_file(&frame_path)?;
+        self.files.push(frame_path);
+        self.timestamps.push(frame.timestamp);
+        Ok(())
+    }
 
-        if frames.is_empty() {
-            return Err("Не удалось извлечь кадры из видео".into());
+    fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
         }
+        self.finished = true;
 
+        // Создаем видео с помощью ffmpeg
+        let mut command = Command::new("ffmpeg");
+        // В stderr остаются только ошибки - их хвост попадает в сообщение
+        command.args(["-y", "-v", "error"]);
 
+        if self.preserve_timestamps {
+            let list_path = self.temp_dir.path().join("frames.ffconcat");
+            write_ffconcat(&list_path, &self.files, &self.timestamps, self.fps.as_f64())?;
+            command
+                .args(["-f", "concat", "-safe", "0", "-i"])
+                .arg(&list_path);
+        } else {
+            let frame_pattern = self.temp_dir.path().join("frame_%06d.png");
+            command
+                .args(["-framerate", &self.fps.to_string(), "-i"])
+                .arg(&frame_pattern);
+        }
 
-        let config = ProcessingConfig {
-            fps,
-            threshold,
-            output_width: frames[0].width(),
-            output_height: frames[0].height(),
-            noise_reduction: true,
-        };
-
-        let mut processor = VideoDerivativeProcessor::new(config);
-        let mut derivative_frames = Vec::new();
-
-        // Обрабатываем каждый кадр
-        for (i, frame) in frames.iter().enumerate() {
-            println!("Обработан кадр {}/{}", i + 1, frames.len());
-
-            let derivative_frame = processor.process_frame(frame);
-            derivative_frames.push(derivative_frame);
+        if let Some(audio) = &self.audio {
+            let first_timestamp = self.timestamps.first().copied().unwrap_or(0.0);
+            command.args(audio.input_args(first_timestamp));
+            command.args(audio.output_args(1));
+        }
+        // Выходные опции - только после всех входов
+        if self.preserve_timestamps {
+            command.args(["-fps_mode", "vfr"]);
         }
 
-        println!("Создание выходного видео...");
-        self.save_frames_to_video(&derivative_frames, output_video_path, fps)?;
+        let mut child = command
+            .args(self.encoder.to_args())
+            .arg(&self.output_path)
+            .stderr(Stdio::piped())
+            .spawn().map_err(Error::spawn)?;
+        let stderr = child.stderr.take().ok_or_else(|| Error::encode("Не удалось открыть stderr FFmpeg"))?;
+        let stderr = spawn_stderr_tail(stderr);
+        let status = wait_cancellable(&mut child, &self.cancellation)?;
+        let stderr = stderr.join().unwrap_or_default();
 
+        if !status.success() {
+            return Err(Error::ffmpeg(status, stderr.join("\n")));
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,92 @@
+//! Частота кадров как точная дробь. У NTSC-видео частота 30000/1001, и
+//! округление до 30 ускоряет результат на 0.1% - около 3.6 с за час
+//! относительно исходника и его звука.
+
+use std::fmt;
+
+/// Наибольший знаменатель при восстановлении дроби из `f64`
+const MAX_DENOMINATOR: u64 = 100_000;
+
+/// Частота кадров `num / den` кадров в секунду
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRate {
+    pub num: u32,
+    pub den: u32,
+}
+
+impl FrameRate {
+    /// Несократимая дробь; нулевые числитель и знаменатель заменяются на 1
+    pub fn new(num: u32, den: u32) -> Self {
+        let (num, den) = (num.max(1), den.max(1));
+        let divisor = gcd(num as u64, den as u64) as u32;
+        Self { num: num / divisor, den: den / divisor }
+    }
+
+    /// Ближайшая дробь со знаменателем не больше 100000 (цепные дроби):
+    /// `29.97002997` от ffprobe превращается обратно в 30000/1001
+    pub fn from_f64(rate: f64) -> Option<Self> {
+        if !rate.is_finite() || rate <= 0.0 || rate > u32::MAX as f64 {
+            return None;
+        }
+        let (mut h0, mut h1) = (0u64, 1u64);
+        let (mut k0, mut k1) = (1u64, 0u64);
+        let mut x = rate;
+        loop {
+            let a = x.floor() as u64;
+            let (h2, k2) = (a * h1 + h0, a * k1 + k0);
+            if k2 > MAX_DENOMINATOR || h2 > u32::MAX as u64 {
+                break;
+            }
+            (h0, h1, k0, k1) = (h1, h2, k1, k2);
+            let fraction = x - a as f64;
+            if fraction < 1e-12 || (h1 as f64 / k1 as f64 - rate).abs() <= rate * 1e-9 {
+                break;
+            }
+            x = 1.0 / fraction;
+        }
+        (k1 > 0 && h1 > 0).then(|| Self::new(h1 as u32, k1 as u32))
+    }
+
+    pub fn as_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Частота после прореживания до каждого `step`-го кадра: 25 / 3 = 25/3
+    pub fn divided_by(self, step: usize) -> Self {
+        let den = self.den as u64 * step.max(1) as u64;
+        let divisor = gcd(self.num as u64, den);
+        match u32::try_from(den / divisor) {
+            Ok(den) => Self::new(self.num / divisor as u32, den),
+            Err(_) => Self::from_f64(self.as_f64() / step as f64).unwrap_or(Self::new(1, u32::MAX)),
+        }
+    }
+
+    /// Ближайшая целая частота - для форматов, где дробную не задать
+    pub fn rounded(self) -> u32 {
+        (self.as_f64().round() as u32).max(1)
+    }
+}
+
+impl From<u32> for FrameRate {
+    fn from(fps: u32) -> Self {
+        Self::new(fps, 1)
+    }
+}
+
+/// В синтаксисе FFmpeg: `30` или `30000/1001`
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
@@ -0,0 +1,238 @@
+//! Последовательности пронумерованных изображений (PNG/JPEG/TIFF/EXR...)
+//! как источник и приемник кадров. Так работают многие скоростные и
+//! микроскопические камеры; FFmpeg для этого не нужен.
+
+use crate::error::{Error, Result};
+use crate::types::VideoFrame;
+use crate::video::stream::{VideoSink, VideoSource};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Расширения файлов, которые читает крейт `image`
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "tif", "tiff", "exr", "bmp", "tga", "webp", "pnm", "ppm", "pgm", "hdr", "qoi",
+];
+
+/// Источник кадров из директории или glob-шаблона (`shots/img_*.tiff`).
+/// Файлы упорядочиваются по номеру в имени, метки времени - по `fps`.
+pub struct ImageSequenceSource {
+    files: Vec<PathBuf>,
+    fps: f64,
+    position: usize,
+}
+
+impl ImageSequenceSource {
+    pub fn open(path: &str, fps: f64) -> Result<Self> {
+        if fps <= 0.0 {
+            return Err(Error::config("Частота кадров последовательности должна быть положительной"));
+        }
+        let files = list_sequence(path)?;
+        if files.is_empty() {
+            return Err(Error::NoFrames(format!("не найдено изображений {}", path)));
+        }
+        Ok(Self::from_files(files, fps))
+    }
+
+    /// Последовательность из уже упорядоченного списка файлов
+    pub fn from_files(files: Vec<PathBuf>, fps: f64) -> Self {
+        Self {
+            files,
+            fps,
+            position: 0,
+        }
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
+
+impl VideoSource for ImageSequenceSource {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        let Some(path) = self.files.get(self.position) else {
+            return Ok(None);
+        };
+        let image = image::open(path)
+            .map_err(|e| Error::decode(format!("Не удалось загрузить {}: {}", path.display(), e)))?
+            .to_rgb8();
+
+        let frame = VideoFrame::new(image, self.position, self.position as f64 / self.fps);
+        self.position += 1;
+        Ok(Some(frame))
+    }
+
+    fn frame_count_hint(&self) -> Option<usize> {
+        Some(self.files.len())
+    }
+}
+
+/// Приемник, сохраняющий кадры как пронумерованные изображения.
+/// Путь - директория (`out/`, кадры `frame_000001.png`) или шаблон
+/// с номером в стиле printf (`out/diff_%04d.tiff`). Формат - по расширению.
+pub struct ImageSequenceSink {
+    directory: PathBuf,
+    prefix: String,
+    suffix: String,
+    digits: usize,
+    next_number: usize,
+}
+
+impl ImageSequenceSink {
+    pub fn create(path: &str) -> Result<Self> {
+        let (directory, prefix, suffix, digits) = match split_printf_pattern(path) {
+            Some(parts) => parts,
+            None => (PathBuf::from(path), "frame_".to_string(), ".png".to_string(), 6),
+        };
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::file(&directory, e))?;
+
+        Ok(Self {
+            directory,
+            prefix,
+            suffix,
+            digits,
+            next_number: 1,
+        })
+    }
+
+    /// Номер первого записываемого файла (по умолчанию 1)
+    pub fn starting_at(mut self, number: usize) -> Self {
+        self.next_number = number;
+        self
+    }
+
+    fn frame_path(&self, number: usize) -> PathBuf {
+        self.directory
+            .join(format!("{}{:0width$}{}", self.prefix, number, self.suffix, width = self.digits))
+    }
+}
+
+impl VideoSink for ImageSequenceSink {
+    fn write_frame(&mut self, frame: &VideoFrame) -> Result<()> {
+        frame.save_to_file(&self.frame_path(self.next_number))?;
+        self.next_number += 1;
+        Ok(())
+    }
+}
+
+/// Похож ли путь на последовательность изображений: директория или glob-шаблон
+pub fn is_image_sequence_input(path: &str) -> bool {
+    path.contains(['*', '?']) || Path::new(path).is_dir()
+}
+
+/// Похож ли путь на выход в последовательность: директория или printf-шаблон
+pub fn is_image_sequence_output(path: &str) -> bool {
+    split_printf_pattern(path).is_some() || path.ends_with('/') || Path::new(path).is_dir()
+}
+
+/// Файлы последовательности в порядке номеров
+pub fn list_sequence(path: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(path);
+    let (directory, pattern) = if path.is_dir() {
+        (path, None)
+    } else {
+        let pattern = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| Error::config(format!("Некорректный шаблон: {}", path.display())))?;
+        let directory = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        (directory, Some(pattern))
+    };
+
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|e| Error::file(directory, e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.is_file() && has_image_extension(file))
+        .filter(|file| {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            pattern.as_deref().is_none_or(|pattern| wildcard_match(pattern, &name))
+        })
+        .collect();
+
+    files.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(files)
+}
+
+fn has_image_extension(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Сопоставление имени с шаблоном, где `*` - любая подстрока, `?` - любой символ
+pub(crate) fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Звездочка поглощает еще один символ
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Сравнение с учетом чисел в именах: `img_9` < `img_10`
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let a_end = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+                let b_end = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+                let (a_num, b_num) = (a[..a_end].trim_start_matches('0'), b[..b_end].trim_start_matches('0'));
+                let order = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a = &a[a_end..];
+                b = &b[b_end..];
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a = &a[x.len_utf8()..];
+                b = &b[y.len_utf8()..];
+            }
+        }
+    }
+}
+
+/// Разбирает `dir/name_%04d.ext` на директорию, префикс, суффикс и ширину номера
+fn split_printf_pattern(path: &str) -> Option<(PathBuf, String, String, usize)> {
+    let path = Path::new(path);
+    let name = path.file_name()?.to_str()?;
+    let start = name.find('%')?;
+    let rest = &name[start + 1..];
+    let end = rest.find('d')?;
+    let width = &rest[..end];
+    let digits = if width.is_empty() {
+        0
+    } else if width.chars().all(|c| c.is_ascii_digit()) {
+        width.parse().ok()?
+    } else {
+        return None;
+    };
+
+    let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let directory = if directory.as_os_str().is_empty() { PathBuf::from(".") } else { directory };
+    Some((directory, name[..start].to_string(), rest[end + 1..].to_string(), digits))
+}
@@ -0,0 +1,284 @@
+//! Живые источники: непрерывный поток из stdin, именованного канала (FIFO)
+//! или файла, который еще дописывается. Кадры выдаются по мере поступления,
+//! без предварительного ffprobe и без ожидания конца потока.
+
+use crate::error::{Error, Result};
+use crate::types::VideoFrame;
+use crate::video::ffmpeg_wrapper::read_raw_frame;
+use crate::video::stream::VideoSource;
+use crate::video::y4m::Y4mReader;
+use image::RgbImage;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Как часто проверять, не появились ли новые данные в дописываемом файле
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Откуда читать поток
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiveInput {
+    Stdin,
+    /// Именованный канал или обычный (в том числе растущий) файл
+    Path(PathBuf),
+}
+
+impl LiveInput {
+    /// `-` означает stdin
+    pub fn parse(input: &str) -> Self {
+        if input == "-" {
+            LiveInput::Stdin
+        } else {
+            LiveInput::Path(PathBuf::from(input))
+        }
+    }
+}
+
+/// Формат живого потока
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LiveFormat {
+    /// Сырые RGB24 кадры фиксированного размера
+    Raw { width: u32, height: u32, fps: f64 },
+    /// YUV4MPEG2 - размер и частота берутся из заголовка
+    Y4m,
+    /// Любой поток, который декодирует FFmpeg (MPEG-TS, MJPEG, H.264, ...)
+    Ffmpeg,
+}
+
+impl FromStr for LiveFormat {
+    type Err = Error;
+
+    /// `raw` требует размер и частоту, поэтому задается через `LiveFormat::Raw`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "y4m" | "yuv4mpeg" => Ok(LiveFormat::Y4m),
+            "ffmpeg" | "auto" => Ok(LiveFormat::Ffmpeg),
+            other => Err(Error::config(format!("Неизвестный формат потока: {} (доступны ffmpeg, y4m, raw)", other))),
+        }
+    }
+}
+
+/// Параметры живого источника
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveConfig {
+    pub input: LiveInput,
+    pub format: LiveFormat,
+    /// Файл еще пишется: в конце данных ждать продолжения
+    pub follow: bool,
+    /// Сколько ждать новых данных в режиме `follow`, прежде чем считать
+    /// поток законченным (`None` - ждать бесконечно)
+    pub idle_timeout: Option<Duration>,
+}
+
+impl LiveConfig {
+    pub fn new(input: LiveInput, format: LiveFormat) -> Self {
+        Self {
+            input,
+            format,
+            follow: false,
+            idle_timeout: Some(Duration::from_secs(10)),
+        }
+    }
+
+    pub fn with_follow(mut self) -> Self {
+        self.follow = true;
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Открывает поток. Для Y4M и FFmpeg вызов ждет заголовка потока.
+    pub fn open(&self) -> Result<LiveSource> {
+        match self.format {
+            LiveFormat::Raw { width, height, fps } => {
+                if width == 0 || height == 0 || fps <= 0.0 {
+                    return Err(Error::config("Для сырого потока нужны размер кадра и частота"));
+                }
+                let source = RawFrameSource::new(self.reader()?, width, height, fps);
+                Ok(LiveSource::new(Box::new(source), fps, None))
+            }
+            LiveFormat::Y4m => {
+                let reader = Y4mReader::new(BufReader::new(self.reader()?))?;
+                let fps = reader.header().fps();
+                Ok(LiveSource::new(Box::new(reader), fps, None))
+            }
+            LiveFormat::Ffmpeg => self.open_ffmpeg(),
+        }
+    }
+
+    fn reader(&self) -> Result<Box<dyn Read + Send>> {
+        Ok(match &self.input {
+            LiveInput::Stdin => Box::new(io::stdin()),
+            LiveInput::Path(path) => {
+                // Открытие FIFO блокируется, пока не подключится пишущая сторона
+                let file = File::open(path)
+                    .map_err(|e| Error::file(path, e))?;
+                if self.follow {
+                    Box::new(FollowReader::new(file, self.idle_timeout))
+                } else {
+                    Box::new(file)
+                }
+            }
+        })
+    }
+
+    /// FFmpeg декодирует поток и отдает его в Y4M через stdout: размер и
+    /// частота приходят в заголовке, поэтому ffprobe (который прочитал бы
+    /// часть stdin) не нужен
+    fn open_ffmpeg(&self) -> Result<LiveSource> {
+        let mut command = Command::new("ffmpeg");
+        command.args(["-hide_banner", "-nostats", "-loglevel", "error"]);
+        // Без буферизации на входе, чтобы кадры уходили сразу после декодирования
+        command.args(["-fflags", "nobuffer", "-flags", "low_delay"]);
+
+        match &self.input {
+            LiveInput::Stdin => {
+                command.args(["-i", "pipe:0"]).stdin(Stdio::inherit());
+            }
+            LiveInput::Path(path) if self.follow => {
+                command.args(["-follow", "1"]);
+                if let Some(timeout) = self.idle_timeout {
+                    command.args(["-rw_timeout", &timeout.as_micros().to_string()]);
+                }
+                command.arg("-i").arg(format!("file:{}", path.display())).stdin(Stdio::null());
+            }
+            LiveInput::Path(path) => {
+                command.arg("-i").arg(path).stdin(Stdio::null());
+            }
+        }
+
+        let mut child = command
+            .args(["-fps_mode", "passthrough", "-pix_fmt", "yuv444p", "-f", "yuv4mpegpipe", "-"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(Error::spawn)?;
+
+        let stdout = child.stdout.take().ok_or_else(|| Error::decode("Не удалось открыть stdout FFmpeg"))?;
+        let reader = match Y4mReader::new(BufReader::new(stdout)) {
+            Ok(reader) => reader,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Error::decode(format!("FFmpeg не смог декодировать поток: {}", e)));
+            }
+        };
+        let fps = reader.header().fps();
+        Ok(LiveSource::new(Box::new(reader), fps, Some(child)))
+    }
+}
+
+/// Открытый живой источник
+pub struct LiveSource {
+    inner: Box<dyn VideoSource + Send>,
+    frame_rate: f64,
+    child: Option<Child>,
+}
+
+impl LiveSource {
+    fn new(inner: Box<dyn VideoSource + Send>, frame_rate: f64, child: Option<Child>) -> Self {
+        Self { inner, frame_rate, child }
+    }
+
+    /// Номинальная частота потока
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+}
+
+impl VideoSource for LiveSource {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        let frame = self.inner.next_frame()?;
+        if frame.is_none()
+            && let Some(mut child) = self.child.take()
+        {
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(Error::ffmpeg(status, "ошибка при чтении потока"));
+            }
+        }
+        Ok(frame)
+    }
+}
+
+impl Drop for LiveSource {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Источник сырых RGB24 кадров из любого потока байтов
+pub struct RawFrameSource<R> {
+    reader: R,
+    width: u32,
+    height: u32,
+    fps: f64,
+    index: usize,
+}
+
+impl<R: Read> RawFrameSource<R> {
+    pub fn new(reader: R, width: u32, height: u32, fps: f64) -> Self {
+        Self {
+            reader,
+            width,
+            height,
+            fps,
+            index: 0,
+        }
+    }
+}
+
+impl<R: Read> VideoSource for RawFrameSource<R> {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        let mut buf = vec![0u8; self.width as usize * self.height as usize * 3];
+        if !read_raw_frame(&mut self.reader, &mut buf)? {
+            return Ok(None);
+        }
+        let data = RgbImage::from_raw(self.width, self.height, buf)
+            .ok_or_else(|| Error::decode("Размер кадра не совпадает с ожидаемым"))?;
+        let i = self.index;
+        self.index += 1;
+        Ok(Some(VideoFrame::new(data, i + 1, i as f64 / self.fps)))
+    }
+}
+
+/// Чтение файла, который еще дописывается: в конце данных ждет, пока
+/// файл вырастет, и считает поток законченным после `idle_timeout` без
+/// новых данных
+pub struct FollowReader<R> {
+    inner: R,
+    idle_timeout: Option<Duration>,
+}
+
+impl<R: Read> FollowReader<R> {
+    pub fn new(inner: R, idle_timeout: Option<Duration>) -> Self {
+        Self { inner, idle_timeout }
+    }
+}
+
+impl<R: Read> Read for FollowReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let started = Instant::now();
+        loop {
+            match self.inner.read(buf)? {
+                0 if !buf.is_empty() => {
+                    if self.idle_timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+                        return Ok(0);
+                    }
+                    thread::sleep(FOLLOW_POLL_INTERVAL);
+                }
+                n => return Ok(n),
+            }
+        }
+    }
+}
@@ -1,2 +1,29 @@
 pub mod ffmpeg_wrapper;
-pub use ffmpeg_wrapper::VideoProcessor;
\ No newline at end of file
+pub mod stream;
+pub mod probe;
+pub mod timestamps;
+pub mod audio;
+pub mod encoder;
+pub mod y4m;
+pub mod image_sequence;
+pub mod animated;
+pub mod range;
+pub mod live;
+pub mod progress;
+pub mod cancel;
+pub mod batch;
+pub mod frame_rate;
+pub use ffmpeg_wrapper::{VideoProcessor, FrameTransport, FfmpegFrameSource, FfmpegVideoSink, PngFrameSource, PngVideoSink};
+pub use stream::{VideoSource, VideoSink, IterSource, process_stream, process_stream_threaded, process_stream_with};
+pub use probe::{probe, MediaInfo, VideoStreamInfo, AudioStreamInfo};
+pub use audio::{AudioMode, AudioTrack};
+pub use encoder::{EncoderConfig, EncoderPixelFormat, RateControl, VideoCodec};
+pub use frame_rate::FrameRate;
+pub use y4m::{Y4mColorspace, Y4mHeader, Y4mReader, Y4mWriter};
+pub use image_sequence::{ImageSequenceSink, ImageSequenceSource};
+pub use animated::{ApngSink, GifSink};
+pub use range::{parse_time, FrameRange, RangedSource};
+pub use live::{FollowReader, LiveConfig, LiveFormat, LiveInput, LiveSource, RawFrameSource};
+pub use progress::{Progress, ProgressSink, ProgressStage, SilentProgress, TerminalProgress};
+pub use cancel::{CancellableSource, CancellationToken};
+pub use batch::{collect_inputs, read_manifest, BatchInput, BatchJob, BatchOutcome, BatchReport, BatchRunner, BatchStatus};
@@ -0,0 +1,278 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Сведения о видеофайле, полученные через ffprobe
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MediaInfo {
+    pub format_name: String,
+    /// Длительность контейнера в секундах
+    pub duration: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub video: Option<VideoStreamInfo>,
+    pub audio_streams: Vec<AudioStreamInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VideoStreamInfo {
+    pub index: u32,
+    pub codec: String,
+    /// Размер кадра в том виде, в каком он хранится в потоке (без учета поворота)
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: Option<String>,
+    pub bit_depth: Option<u32>,
+    /// Базовая частота кадров потока (`r_frame_rate`)
+    pub frame_rate: Option<f64>,
+    /// Средняя частота кадров (`avg_frame_rate`), отличается от базовой у VFR-видео
+    pub avg_frame_rate: Option<f64>,
+    pub frame_count: Option<u64>,
+    pub duration: Option<f64>,
+    /// Поворот при отображении в градусах (0, 90, 180, 270)
+    pub rotation: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioStreamInfo {
+    pub index: u32,
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub duration: Option<f64>,
+    pub bit_rate: Option<u64>,
+}
+
+impl MediaInfo {
+    /// Разбирает вывод `ffprobe -print_format json -show_format -show_streams`
+    pub fn from_ffprobe_json(json: &str) -> Result<Self> {
+        let output: ProbeOutput = serde_json::from_str(json)?;
+
+        let video = output
+            .streams
+            .iter()
+            .find(|s| s.codec_type.as_deref() == Some("video") && !s.is_attached_picture())
+            .map(VideoStreamInfo::from_stream);
+
+        let audio_streams = output
+            .streams
+            .iter()
+            .filter(|s| s.codec_type.as_deref() == Some("audio"))
+            .map(AudioStreamInfo::from_stream)
+            .collect();
+
+        let format = output.format.unwrap_or_default();
+        Ok(Self {
+            format_name: format.format_name.unwrap_or_default(),
+            duration: parse_number(format.duration.as_deref()),
+            bit_rate: parse_number(format.bit_rate.as_deref()),
+            video,
+            audio_streams,
+        })
+    }
+
+    pub fn has_audio(&self) -> bool {
+        !self.audio_streams.is_empty()
+    }
+}
+
+impl VideoStreamInfo {
+    fn from_stream(stream: &ProbeStream) -> Self {
+        let frame_rate = parse_rate(stream.r_frame_rate.as_deref());
+        let avg_frame_rate = parse_rate(stream.avg_frame_rate.as_deref());
+        let duration: Option<f64> = parse_number(stream.duration.as_deref());
+
+        // nb_frames есть не во всех контейнерах, иначе оцениваем по длительности
+        let frame_count = parse_number(stream.nb_frames.as_deref()).or_else(|| {
+            let rate = avg_frame_rate.or(frame_rate)?;
+            Some((duration? * rate).round() as u64)
+        });
+
+        let bit_depth = parse_number(stream.bits_per_raw_sample.as_deref())
+            .or_else(|| stream.pix_fmt.as_deref().map(bit_depth_from_pix_fmt));
+
+        Self {
+            index: stream.index,
+            codec: stream.codec_name.clone().unwrap_or_default(),
+            width: stream.width.unwrap_or(0),
+            height: stream.height.unwrap_or(0),
+            pixel_format: stream.pix_fmt.clone(),
+            bit_depth,
+            frame_rate,
+            avg_frame_rate,
+            frame_count,
+            duration,
+            rotation: stream.rotation(),
+        }
+    }
+
+    /// Размер кадра после применения поворота (так его отдает FFmpeg при декодировании)
+    pub fn display_dimensions(&self) -> (u32, u32) {
+        if self.rotation % 180 != 0 {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    /// Частота кадров для расчета меток времени: средняя, если известна, иначе базовая
+    pub fn effective_frame_rate(&self) -> Option<f64> {
+        self.avg_frame_rate.or(self.frame_rate)
+    }
+
+    /// Признак переменной частоты кадров
+    pub fn is_variable_frame_rate(&self) -> bool {
+        match (self.frame_rate, self.avg_frame_rate) {
+            (Some(base), Some(avg)) => (base - avg).abs() > 0.01,
+            _ => false,
+        }
+    }
+}
+
+impl AudioStreamInfo {
+    fn from_stream(stream: &ProbeStream) -> Self {
+        Self {
+            index: stream.index,
+            codec: stream.codec_name.clone().unwrap_or_default(),
+            sample_rate: parse_number(stream.sample_rate.as_deref()),
+            channels: stream.channels,
+            channel_layout: stream.channel_layout.clone(),
+            duration: parse_number(stream.duration.as_deref()),
+            bit_rate: parse_number(stream.bit_rate.as_deref()),
+        }
+    }
+}
+
+/// Запускает ffprobe и возвращает сведения о файле
+pub fn probe(path: &str) -> Result<MediaInfo> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-print_format", "json",
+            "-show_format",
+            "-show_streams",
+            path,
+        ])
+        .output()
+        .map_err(Error::spawn)?;
+
+    if !output.status.success() {
+        return Err(Error::ffmpeg(
+            output.status,
+            format!("ffprobe не смог прочитать {}: {}", path, String::from_utf8_lossy(&output.stderr).trim()),
+        ));
+    }
+
+    MediaInfo::from_ffprobe_json(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Проверяет, что ffprobe установлен
+pub fn is_ffprobe_available() -> bool {
+    Command::new("ffprobe")
+        .arg("-version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProbeFormat {
+    format_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProbeStream {
+    #[serde(default)]
+    index: u32,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    pix_fmt: Option<String>,
+    bits_per_raw_sample: Option<String>,
+    r_frame_rate: Option<String>,
+    avg_frame_rate: Option<String>,
+    nb_frames: Option<String>,
+    duration: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    disposition: HashMap<String, i32>,
+    #[serde(default)]
+    side_data_list: Vec<serde_json::Value>,
+}
+
+impl ProbeStream {
+    fn is_attached_picture(&self) -> bool {
+        self.disposition.get("attached_pic").copied().unwrap_or(0) != 0
+    }
+
+    /// Поворот из side data (новые версии FFmpeg) или из тега `rotate` (старые)
+    fn rotation(&self) -> i32 {
+        let side_data = self
+            .side_data_list
+            .iter()
+            .find_map(|data| data.get("rotation").and_then(|r| r.as_f64()));
+        let degrees = side_data
+            .map(|r| r.round() as i32)
+            .or_else(|| self.tags.get("rotate").and_then(|r| r.parse().ok()))
+            .unwrap_or(0);
+        degrees.rem_euclid(360)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&str>) -> Option<T> {
+    value?.trim().parse().ok()
+}
+
+/// Разбирает частоту вида `30000/1001`
+fn parse_rate(value: Option<&str>) -> Option<f64> {
+    let value = value?;
+    let rate = match value.split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().ok()?;
+            let den: f64 = den.trim().parse().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num / den
+        }
+        None => value.trim().parse().ok()?,
+    };
+    (rate > 0.0).then_some(rate)
+}
+
+/// Битность из имени формата пикселей: `yuv420p10le` -> 10, `gray16be` -> 16, `rgb48le` -> 16
+fn bit_depth_from_pix_fmt(pix_fmt: &str) -> u32 {
+    let name = pix_fmt.trim_end_matches("le").trim_end_matches("be");
+    let split = name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (prefix, digits) = name.split_at(split);
+    let Ok(bits) = digits.parse::<u32>() else {
+        return 8;
+    };
+
+    if prefix.starts_with("rgb") || prefix.starts_with("bgr") {
+        // rgb24/rgb48/rgba64 задают биты на пиксель, а не на компоненту
+        let channels = if prefix.ends_with('a') { 4 } else { 3 };
+        (bits / channels).max(8)
+    } else if prefix.ends_with('p') || prefix.starts_with("gray") || prefix == "p0" || prefix == "y" {
+        // Планарные форматы (yuv420p10, gbrp12), gray16, p010, y210
+        bits
+    } else {
+        8
+    }
+}
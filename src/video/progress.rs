@@ -0,0 +1,280 @@
+//! Отчеты о ходе обработки. Библиотека ничего не печатает сама: сведения
+//! о кадрах уходят в `ProgressSink`, который задает вызывающая сторона.
+
+use crate::error::Result;
+use crate::types::VideoFrame;
+use crate::video::stream::VideoSink;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Этап обработки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStage {
+    /// Чтение кадров без обработки (`extract_frames_from_video`)
+    Decoding,
+    /// Построение производных
+    Processing,
+    /// Запись готовых кадров и завершение кодирования
+    Encoding,
+}
+
+impl ProgressStage {
+    pub fn label(self) -> &'static str {
+        match self {
+            ProgressStage::Decoding => "Декодирование",
+            ProgressStage::Processing => "Обработка",
+            ProgressStage::Encoding => "Кодирование",
+        }
+    }
+}
+
+/// Состояние этапа на момент отчета
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub stage: ProgressStage,
+    /// Сколько кадров этапа уже пройдено
+    pub current: usize,
+    /// Ожидаемое число кадров, если оно известно заранее
+    pub total: Option<usize>,
+    /// Время с начала этапа
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Доля выполненной работы 0..=1
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|&total| total > 0)
+            .map(|total| (self.current as f64 / total as f64).min(1.0))
+    }
+
+    /// Оценка оставшегося времени по средней скорости этапа
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        if self.current == 0 {
+            return None;
+        }
+        let per_frame = self.elapsed.as_secs_f64() / self.current as f64;
+        Some(Duration::from_secs_f64(per_frame * total.saturating_sub(self.current) as f64))
+    }
+}
+
+/// Получатель отчетов о ходе обработки. Вызывается из потока обработки
+/// после каждого кадра, поэтому должен работать быстро.
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, progress: &Progress);
+
+    /// Операция завершена (успешно или с ошибкой)
+    fn finish(&self) {}
+}
+
+/// Замыкание тоже может принимать отчеты
+impl<F: Fn(&Progress) + Send + Sync> ProgressSink for F {
+    fn report(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Ничего не выводит; используется по умолчанию
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentProgress;
+
+impl ProgressSink for SilentProgress {
+    fn report(&self, _progress: &Progress) {}
+}
+
+/// Полоса прогресса в stderr. Если stderr не терминал (например,
+/// перенаправлен в журнал), печатается только начало каждого этапа и итог.
+pub struct TerminalProgress {
+    state: Mutex<TerminalState>,
+    width: usize,
+    interactive: bool,
+}
+
+struct TerminalState {
+    started: Option<Instant>,
+    stage: Option<ProgressStage>,
+    last_draw: Option<Instant>,
+    last: Option<Progress>,
+}
+
+impl TerminalProgress {
+    /// Не чаще одной перерисовки за этот интервал
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(TerminalState { started: None, stage: None, last_draw: None, last: None }),
+            width: 30,
+            interactive: io::stderr().is_terminal(),
+        }
+    }
+
+    /// Ширина полосы в символах
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    fn render(&self, progress: &Progress) -> String {
+        let counter = match progress.total {
+            Some(total) => format!("{}/{}", progress.current, total),
+            None => progress.current.to_string(),
+        };
+        let mut line = match progress.fraction() {
+            Some(fraction) => {
+                let filled = (fraction * self.width as f64).round() as usize;
+                format!(
+                    "{} [{}{}] {} {:>3}%",
+                    progress.stage.label(),
+                    "#".repeat(filled),
+                    "-".repeat(self.width - filled),
+                    counter,
+                    (fraction * 100.0).round() as u32
+                )
+            }
+            None if progress.current == 0 => format!("{}...", progress.stage.label()),
+            None => format!("{}: кадр {}", progress.stage.label(), counter),
+        };
+        if let Some(eta) = progress.eta() {
+            line.push_str(&format!(" осталось {}", format_duration(eta)));
+        }
+        line
+    }
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressSink for TerminalProgress {
+    fn report(&self, progress: &Progress) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let mut stderr = io::stderr().lock();
+        let stage_changed = state.stage != Some(progress.stage);
+        state.started.get_or_insert_with(Instant::now);
+
+        if !self.interactive {
+            if stage_changed {
+                let _ = writeln!(stderr, "{}...", progress.stage.label());
+            }
+        } else {
+            let due = state.last_draw.is_none_or(|last| last.elapsed() >= Self::REDRAW_INTERVAL);
+            let complete = progress.total.is_some_and(|total| progress.current >= total);
+            if stage_changed && state.stage.is_some() {
+                let _ = writeln!(stderr);
+            }
+            if stage_changed || due || complete {
+                // \x1b[K стирает остаток предыдущей, более длинной строки
+                let _ = write!(stderr, "\r{}\x1b[K", self.render(progress));
+                let _ = stderr.flush();
+                state.last_draw = Some(Instant::now());
+            }
+        }
+        state.stage = Some(progress.stage);
+        state.last = Some(*progress);
+    }
+
+    fn finish(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let mut stderr = io::stderr().lock();
+        if let Some(last) = state.last.take() {
+            if self.interactive {
+                // Последний кадр мог не попасть в отрисовку из-за интервала
+                let _ = writeln!(stderr, "\r{}\x1b[K", self.render(&last));
+            } else if let Some(started) = state.started {
+                let _ = writeln!(stderr, "Готово за {}", format_duration(started.elapsed()));
+            }
+        }
+        state.started = None;
+        state.stage = None;
+        state.last_draw = None;
+    }
+}
+
+/// `М:СС` или `Ч:ММ:СС`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Считает кадры этапа и отправляет отчеты
+pub(crate) struct ProgressTracker {
+    sink: Arc<dyn ProgressSink>,
+    stage: ProgressStage,
+    current: usize,
+    total: Option<usize>,
+    started: Instant,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(sink: Arc<dyn ProgressSink>, stage: ProgressStage, total: Option<usize>) -> Self {
+        Self {
+            sink,
+            stage,
+            current: 0,
+            total,
+            started: Instant::now(),
+        }
+    }
+
+    /// Переход к следующему этапу со сбросом счетчика и времени
+    pub(crate) fn start_stage(&mut self, stage: ProgressStage, total: Option<usize>) {
+        self.stage = stage;
+        self.current = 0;
+        self.total = total;
+        self.started = Instant::now();
+        self.send();
+    }
+
+    pub(crate) fn advance(&mut self) {
+        self.current += 1;
+        self.send();
+    }
+
+    fn send(&self) {
+        self.sink.report(&Progress {
+            stage: self.stage,
+            current: self.current,
+            total: self.total,
+            elapsed: self.started.elapsed(),
+        });
+    }
+}
+
+impl Drop for ProgressTracker {
+    fn drop(&mut self) {
+        self.sink.finish();
+    }
+}
+
+/// Приемник, отмечающий каждый записанный кадр. `finish` переводит отчет
+/// на этап кодирования: для PNG-транспорта и APNG основная работа идет там.
+pub(crate) struct ProgressVideoSink<K> {
+    pub(crate) inner: K,
+    pub(crate) tracker: ProgressTracker,
+}
+
+impl<K: VideoSink> VideoSink for ProgressVideoSink<K> {
+    fn write_frame(&mut self, frame: &VideoFrame) -> Result<()> {
+        self.inner.write_frame(frame)?;
+        self.tracker.advance();
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.tracker.start_stage(ProgressStage::Encoding, None);
+        self.inner.finish()
+    }
+}
@@ -0,0 +1,250 @@
+//! Выбор фрагмента видео: начало, конец или длительность, ограничение
+//! количества кадров и прореживание (каждый N-й кадр).
+//!
+//! Для FFmpeg-источников интервал передается как входные `-ss`/`-t`, поэтому
+//! декодируется только нужный фрагмент. Метки времени кадров остаются на
+//! шкале исходника, чтобы звук и события совпадали с оригиналом.
+
+use crate::error::{Error, Result};
+use crate::types::{FramePixel, VideoFrame};
+use crate::video::stream::VideoSource;
+
+/// Допуск при сравнении меток времени с границами интервала
+const EPSILON: f64 = 1e-6;
+
+/// Фрагмент видео для извлечения
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameRange {
+    /// Начало в секундах от начала файла
+    pub start: Option<f64>,
+    /// Конец в секундах (не включая). Взаимоисключающий с `duration`.
+    pub end: Option<f64>,
+    pub duration: Option<f64>,
+    /// Не больше стольких кадров (после прореживания)
+    pub max_frames: Option<usize>,
+    /// Брать каждый `step`-й кадр (1 - все кадры)
+    pub step: usize,
+}
+
+impl Default for FrameRange {
+    fn default() -> Self {
+        Self {
+            start: None,
+            end: None,
+            duration: None,
+            max_frames: None,
+            step: 1,
+        }
+    }
+}
+
+impl FrameRange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn starting_at(mut self, seconds: f64) -> Self {
+        self.start = Some(seconds);
+        self
+    }
+
+    pub fn ending_at(mut self, seconds: f64) -> Self {
+        self.end = Some(seconds);
+        self
+    }
+
+    pub fn with_duration(mut self, seconds: f64) -> Self {
+        self.duration = Some(seconds);
+        self
+    }
+
+    pub fn with_max_frames(mut self, frames: usize) -> Self {
+        self.max_frames = Some(frames);
+        self
+    }
+
+    pub fn every_nth(mut self, step: usize) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Весь файл без прореживания
+    pub fn is_full(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn start_seconds(&self) -> f64 {
+        self.start.unwrap_or(0.0)
+    }
+
+    /// Конец интервала на шкале исходника
+    pub fn end_seconds(&self) -> Option<f64> {
+        self.end.or_else(|| self.duration.map(|duration| self.start_seconds() + duration))
+    }
+
+    /// Длительность интервала, если его конец задан
+    pub fn duration_seconds(&self) -> Option<f64> {
+        self.duration.or_else(|| self.end.map(|end| end - self.start_seconds()))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(start) = self.start
+            && (!start.is_finite() || start < 0.0)
+        {
+            return Err(Error::config(format!("Некорректное начало фрагмента: {}", start)));
+        }
+        if self.end.is_some() && self.duration.is_some() {
+            return Err(Error::config("Конец и длительность фрагмента задаются по отдельности"));
+        }
+        if let Some(duration) = self.duration_seconds()
+            && (!duration.is_finite() || duration <= 0.0)
+        {
+            return Err(Error::config("Конец фрагмента должен быть позже начала"));
+        }
+        if self.step == 0 {
+            return Err(Error::config("Шаг прореживания должен быть не меньше 1"));
+        }
+        if self.max_frames == Some(0) {
+            return Err(Error::config("Максимальное количество кадров должно быть больше нуля"));
+        }
+        Ok(())
+    }
+
+    /// Попадает ли метка времени в интервал
+    pub fn contains(&self, timestamp: f64) -> bool {
+        timestamp + EPSILON >= self.start_seconds()
+            && self.end_seconds().is_none_or(|end| timestamp + EPSILON < end)
+    }
+
+    /// Оценка количества кадров фрагмента по числу кадров и частоте файла
+    pub fn frame_count_hint(&self, total: Option<usize>, frame_rate: f64) -> Option<usize> {
+        let skipped = (self.start_seconds() * frame_rate).round() as usize;
+        let in_range = match (self.duration_seconds(), total) {
+            (Some(duration), total) => {
+                let count = (duration * frame_rate).round() as usize;
+                total.map_or(count, |total| count.min(total.saturating_sub(skipped)))
+            }
+            (None, Some(total)) => total.saturating_sub(skipped),
+            (None, None) => return self.max_frames,
+        };
+        let count = in_range.div_ceil(self.step.max(1));
+        Some(self.max_frames.map_or(count, |max| count.min(max)))
+    }
+
+    /// Входные аргументы FFmpeg (ставятся перед `-i`): поиск по ключевым
+    /// кадрам без декодирования всего, что лежит до начала фрагмента
+    pub(crate) fn input_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.start_seconds() > 0.0 {
+            args.extend(["-ss".to_string(), format!("{:.6}", self.start_seconds())]);
+        }
+        if let Some(duration) = self.duration_seconds() {
+            args.extend(["-t".to_string(), format!("{:.6}", duration)]);
+        }
+        args
+    }
+
+    /// Цепочка фильтров: прореживание до `showinfo`, чтобы PTS печатались
+    /// только для оставшихся кадров
+    pub(crate) fn filter(&self) -> String {
+        if self.step > 1 {
+            format!("select=not(mod(n\\,{})),showinfo", self.step)
+        } else {
+            "showinfo".to_string()
+        }
+    }
+
+    /// Выходные аргументы FFmpeg
+    pub(crate) fn output_args(&self) -> Vec<String> {
+        match self.max_frames {
+            Some(frames) => vec!["-frames:v".to_string(), frames.to_string()],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Разбирает время в виде секунд (`90.5`), `ММ:СС` или `ЧЧ:ММ:СС.мс`
+pub fn parse_time(text: &str) -> Result<f64> {
+    let invalid = || Error::config(format!("Некорректное время: {}", text));
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: f64 = part.parse().map_err(|_| invalid())?;
+        // Часы и минуты - целые, секунды могут быть дробными
+        let last = i + 1 == parts.len();
+        if !value.is_finite() || value < 0.0 || (!last && value.fract() != 0.0) || (i > 0 && value >= 60.0) {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Ok(seconds)
+}
+
+/// Источник, отбирающий кадры фрагмента по меткам времени. Нужен для
+/// источников без FFmpeg (Y4M, последовательности изображений): они
+/// читаются с начала, а кадры вне интервала пропускаются.
+pub struct RangedSource<S> {
+    source: S,
+    range: FrameRange,
+    /// Кадров внутри интервала, включая пропущенные прореживанием
+    seen: usize,
+    emitted: usize,
+    finished: bool,
+}
+
+impl<S> RangedSource<S> {
+    pub fn new(source: S, range: FrameRange) -> Self {
+        Self {
+            source,
+            range,
+            seen: 0,
+            emitted: 0,
+            finished: false,
+        }
+    }
+}
+
+impl<P: FramePixel, S: VideoSource<P>> VideoSource<P> for RangedSource<S> {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame<P>>> {
+        if self.finished || self.range.max_frames.is_some_and(|max| self.emitted >= max) {
+            self.finished = true;
+            return Ok(None);
+        }
+
+        while let Some(mut frame) = self.source.next_frame()? {
+            if frame.timestamp + EPSILON < self.range.start_seconds() {
+                continue;
+            }
+            if !self.range.contains(frame.timestamp) {
+                self.finished = true;
+                return Ok(None);
+            }
+
+            let index = self.seen;
+            self.seen += 1;
+            if !index.is_multiple_of(self.range.step.max(1)) {
+                continue;
+            }
+
+            self.emitted += 1;
+            frame.frame_number = self.emitted;
+            return Ok(Some(frame));
+        }
+
+        self.finished = true;
+        Ok(None)
+    }
+
+    fn frame_count_hint(&self) -> Option<usize> {
+        // Частота источника неизвестна, поэтому оцениваем только без интервала
+        if self.range.start.is_none() && self.range.end_seconds().is_none() {
+            self.range.frame_count_hint(self.source.frame_count_hint(), 1.0)
+        } else {
+            self.range.max_frames
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error::Result;
+use crate::processors::VideoDerivativeProcessor;
+use crate::types::{FramePixel, VideoFrame};
+use image::Rgb;
+use std::sync::mpsc;
+use std::thread;
+
+/// Источник кадров, читающий их по одному
+pub trait VideoSource<P: FramePixel = Rgb<u8>> {
+    /// Возвращает следующий кадр или `None` в конце потока
+    fn next_frame(&mut self) -> Result<Option<VideoFrame<P>>>;
+
+    /// Ожидаемое количество кадров, если оно известно заранее
+    fn frame_count_hint(&self) -> Option<usize> {
+        None
+    }
+
+    /// Превращает источник в итератор кадров
+    fn frames(self) -> SourceFrames<Self, P>
+    where
+        Self: Sized,
+    {
+        SourceFrames { source: self, _pixel: std::marker::PhantomData }
+    }
+}
+
+/// Приемник кадров: кодировщик, файл, коллекция и т.д.
+pub trait VideoSink<P: FramePixel = Rgb<u8>> {
+    fn write_frame(&mut self, frame: &VideoFrame<P>) -> Result<()>;
+
+    /// Завершает запись (сбрасывает буферы, запускает кодирование и т.д.)
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<P: FramePixel, S: VideoSource<P> + ?Sized> VideoSource<P> for Box<S> {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame<P>>> {
+        (**self).next_frame()
+    }
+
+    fn frame_count_hint(&self) -> Option<usize> {
+        (**self).frame_count_hint()
+    }
+}
+
+impl<P: FramePixel, S: VideoSink<P> + ?Sized> VideoSink<P> for Box<S> {
+    fn write_frame(&mut self, frame: &VideoFrame<P>) -> Result<()> {
+        (**self).write_frame(frame)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        (**self).finish()
+    }
+}
+
+/// Итератор по кадрам источника
+pub struct SourceFrames<S, P> {
+    source: S,
+    _pixel: std::marker::PhantomData<P>,
+}
+
+impl<P: FramePixel, S: VideoSource<P>> Iterator for SourceFrames<S, P> {
+    type Item = Result<VideoFrame<P>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.source.next_frame().transpose()
+    }
+}
+
+/// Источник поверх любого итератора кадров (например, `FrameGenerator` или `Vec`)
+pub struct IterSource<I> {
+    frames: I,
+}
+
+impl<I> IterSource<I> {
+    pub fn new<T: IntoIterator<IntoIter = I>>(frames: T) -> Self {
+        Self { frames: frames.into_iter() }
+    }
+}
+
+impl<P: FramePixel, I: Iterator<Item = VideoFrame<P>>> VideoSource<P> for IterSource<I> {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame<P>>> {
+        Ok(self.frames.next())
+    }
+
+    fn frame_count_hint(&self) -> Option<usize> {
+        match self.frames.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper),
+            _ => None,
+        }
+    }
+}
+
+/// Приемник, собирающий кадры в память
+impl<P: FramePixel> VideoSink<P> for Vec<VideoFrame<P>> {
+    fn write_frame(&mut self, frame: &VideoFrame<P>) -> Result<()> {
+        self.push(frame.clone());
+        Ok(())
+    }
+}
+
+/// Прогоняет все кадры источника через процессор в приемник.
+/// В памяти одновременно находятся только текущий и предыдущий кадры.
+/// Возвращает количество обработанных кадров.
+pub fn process_stream<P, S, K>(
+    source: &mut S,
+    processor: &mut VideoDerivativeProcessor<P>,
+    sink: &mut K,
+) -> Result<usize>
+where
+    P: FramePixel,
+    S: VideoSource<P> + ?Sized,
+    K: VideoSink<P> + ?Sized,
+{
+    let mut count = 0;
+    while let Some(frame) = source.next_frame()? {
+        sink.write_frame(&processor.process_frame(&frame))?;
+        count += 1;
+    }
+    sink.finish()?;
+    Ok(count)
+}
+
+/// Как `process_stream`, но в приемник попадает результат `compose(исходный
+/// кадр, производная)` - например, раскладка `CompositeLayout`. Исходный кадр
+/// приводится к геометрии производной (`ProcessingConfig::geometry`).
+pub fn process_stream_with<P, S, K, F>(
+    source: &mut S,
+    processor: &mut VideoDerivativeProcessor<P>,
+    sink: &mut K,
+    mut compose: F,
+) -> Result<usize>
+where
+    P: FramePixel,
+    S: VideoSource<P> + ?Sized,
+    K: VideoSink<P> + ?Sized,
+    F: FnMut(&VideoFrame<P>, VideoFrame<P>) -> VideoFrame<P>,
+{
+    let mut count = 0;
+    while let Some(frame) = source.next_frame()? {
+        let (original, derivative) = processor.process_frame_with_original(frame);
+        sink.write_frame(&compose(&original, derivative))?;
+        count += 1;
+    }
+    sink.finish()?;
+    Ok(count)
+}
+
+/// То же, что `process_stream`, но декодирование идет в отдельном потоке.
+/// Между потоками передается не более `capacity` кадров, поэтому память ограничена.
+pub fn process_stream_threaded<P, S, K>(
+    source: S,
+    processor: &mut VideoDerivativeProcessor<P>,
+    sink: &mut K,
+    capacity: usize,
+) -> Result<usize>
+where
+    P: FramePixel,
+    S: VideoSource<P> + Send,
+    K: VideoSink<P> + ?Sized,
+{
+    let (sender, receiver) = mpsc::sync_channel::<Result<VideoFrame<P>>>(capacity.max(1));
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            let mut source = source;
+            loop {
+                let message = match source.next_frame() {
+                    Ok(Some(frame)) => Ok(frame),
+                    Ok(None) => break,
+                    Err(e) => Err(e),
+                };
+                let failed = message.is_err();
+                // Получатель закрыл канал - дальше читать незачем
+                if sender.send(message).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let mut count = 0;
+        for message in receiver {
+            let frame = message?;
+            sink.write_frame(&processor.process_frame(&frame))?;
+            count += 1;
+        }
+        sink.finish()?;
+        Ok(count)
+    })
+}
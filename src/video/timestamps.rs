@@ -0,0 +1,122 @@
+//! Метки времени кадров: чтение PTS из фильтра `showinfo` и запись
+//! списков `ffconcat` с длительностями для кодирования VFR-видео. Здесь же
+//! хранится хвост stderr FFmpeg для сообщений об ошибках.
+
+use crate::error::Result;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+/// Сколько последних строк stderr FFmpeg сохранять для сообщений об ошибках
+const STDERR_TAIL: usize = 20;
+
+/// Извлекает `pts_time` из строки лога фильтра `showinfo`
+pub fn parse_showinfo_pts(line: &str) -> Option<f64> {
+    if !line.contains("showinfo") {
+        return None;
+    }
+    let rest = &line[line.find("pts_time:")? + "pts_time:".len()..];
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Извлекает номер кадра `n` и `pts_time` из строки лога `showinfo`
+pub fn parse_showinfo_frame(line: &str) -> Option<(usize, f64)> {
+    let pts = parse_showinfo_pts(line)?;
+    let rest = &line[line.find(" n:")? + " n:".len()..];
+    Some((rest.split_whitespace().next()?.parse().ok()?, pts))
+}
+
+/// Читает stderr FFmpeg в отдельном потоке: номера кадров и метки времени
+/// из `showinfo` отправляются в канал, остальные строки сохраняются для диагностики
+pub fn spawn_pts_reader<R: Read + Send + 'static>(stderr: R) -> (Receiver<(usize, f64)>, JoinHandle<Vec<String>>) {
+    let (sender, receiver) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        let mut tail = Vec::new();
+        for line in BufReader::new(stderr).lines() {
+            let Ok(line) = line else { break };
+            match parse_showinfo_frame(&line) {
+                Some(frame) => {
+                    let _ = sender.send(frame);
+                }
+                None if !line.contains("showinfo") => {
+                    if tail.len() == STDERR_TAIL {
+                        tail.remove(0);
+                    }
+                    tail.push(line);
+                }
+                None => {}
+            }
+        }
+        tail
+    });
+
+    (receiver, handle)
+}
+
+/// Читает stderr FFmpeg в отдельном потоке и сохраняет последние строки,
+/// чтобы процесс не встал на полном канале
+pub fn spawn_stderr_tail<R: Read + Send + 'static>(stderr: R) -> JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let mut tail = Vec::new();
+        for line in BufReader::new(stderr).lines() {
+            let Ok(line) = line else { break };
+            if tail.len() == STDERR_TAIL {
+                tail.remove(0);
+            }
+            tail.push(line);
+        }
+        tail
+    })
+}
+
+/// Проверяет, что кадры идут с постоянным шагом (с допуском в 1% от шага)
+pub fn is_uniform(timestamps: &[f64]) -> bool {
+    if timestamps.len() < 3 {
+        return true;
+    }
+    let first = timestamps[1] - timestamps[0];
+    timestamps
+        .windows(2)
+        .all(|pair| ((pair[1] - pair[0]) - first).abs() <= first.abs() * 0.01)
+}
+
+/// Длительности кадров по меткам времени; последний кадр получает
+/// длительность предыдущего (или `1 / fallback_fps` для одиночного кадра)
+pub fn frame_durations(timestamps: &[f64], fallback_fps: f64) -> Vec<f64> {
+    let default = 1.0 / fallback_fps.max(1e-3);
+    let mut durations: Vec<f64> = timestamps
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .collect();
+    let last = durations.last().copied().unwrap_or(default);
+    if !timestamps.is_empty() {
+        durations.push(last);
+    }
+    durations
+}
+
+/// Записывает список `ffconcat` для concat-демультиплексора FFmpeg
+pub fn write_ffconcat(
+    list_path: &Path,
+    files: &[PathBuf],
+    timestamps: &[f64],
+    fallback_fps: f64,
+) -> Result<()> {
+    let mut list = String::from("ffconcat version 1.0\n");
+    for (file, duration) in files.iter().zip(frame_durations(timestamps, fallback_fps)) {
+        list.push_str(&format!("file '{}'\nduration {:.6}\n", escape_path(file), duration));
+    }
+    // Последний файл повторяется, иначе concat игнорирует его длительность
+    if let Some(last) = files.last() {
+        list.push_str(&format!("file '{}'\n", escape_path(last)));
+    }
+    fs::write(list_path, list)?;
+    Ok(())
+}
+
+fn escape_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\'', "'\\''")
+}
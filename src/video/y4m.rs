@@ -0,0 +1,397 @@
+//! Чтение и запись YUV4MPEG2 (`.y4m`) без FFmpeg.
+//!
+//! Поддерживаются 8-битные форматы `420*`, `422`, `444` и `mono`.
+//! Цвет переводится по BT.601; диапазон - ограниченный (16..235),
+//! если в заголовке нет `XCOLORRANGE=FULL`.
+
+use crate::error::{Error, Result};
+use crate::types::VideoFrame;
+use crate::video::stream::{VideoSink, VideoSource};
+use image::{Rgb, RgbImage};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+const MAGIC: &str = "YUV4MPEG2";
+
+/// Предел площади кадра (16384x16384): больший размер в заголовке считаем
+/// поврежденным, а не пытаемся выделить под него буфер
+const MAX_FRAME_PIXELS: usize = 1 << 28;
+
+/// Прореживание цветности в файле Y4M
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Y4mColorspace {
+    #[default]
+    C420,
+    C422,
+    C444,
+    /// Только яркость
+    Mono,
+}
+
+impl Y4mColorspace {
+    fn parse(tag: &str) -> Result<Self> {
+        match tag {
+            "420" | "420jpeg" | "420paldv" | "420mpeg2" => Ok(Y4mColorspace::C420),
+            "422" => Ok(Y4mColorspace::C422),
+            "444" => Ok(Y4mColorspace::C444),
+            "mono" => Ok(Y4mColorspace::Mono),
+            other => Err(Error::decode(format!("Неподдерживаемый формат Y4M: C{}", other))),
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            Y4mColorspace::C420 => "420jpeg",
+            Y4mColorspace::C422 => "422",
+            Y4mColorspace::C444 => "444",
+            Y4mColorspace::Mono => "mono",
+        }
+    }
+
+    /// Размер плоскости цветности (`None` для `mono`)
+    fn chroma_size(self, width: u32, height: u32) -> Option<(u32, u32)> {
+        match self {
+            Y4mColorspace::C420 => Some((width.div_ceil(2), height.div_ceil(2))),
+            Y4mColorspace::C422 => Some((width.div_ceil(2), height)),
+            Y4mColorspace::C444 => Some((width, height)),
+            Y4mColorspace::Mono => None,
+        }
+    }
+
+    fn frame_len(self, width: u32, height: u32) -> usize {
+        let luma = width as usize * height as usize;
+        let chroma = self.chroma_size(width, height).map_or(0, |(w, h)| w as usize * h as usize);
+        luma + 2 * chroma
+    }
+}
+
+/// Параметры потока из заголовка Y4M
+#[derive(Debug, Clone, PartialEq)]
+pub struct Y4mHeader {
+    pub width: u32,
+    pub height: u32,
+    /// Частота кадров как дробь `числитель:знаменатель`
+    pub frame_rate: (u32, u32),
+    pub colorspace: Y4mColorspace,
+    /// Полный диапазон 0..255 вместо ограниченного 16..235
+    pub full_range: bool,
+}
+
+impl Y4mHeader {
+    pub fn fps(&self) -> f64 {
+        self.frame_rate.0 as f64 / self.frame_rate.1.max(1) as f64
+    }
+
+    fn parse(line: &str) -> Result<Self> {
+        let mut params = line.split_ascii_whitespace();
+        if params.next() != Some(MAGIC) {
+            return Err(Error::decode("Файл не является YUV4MPEG2"));
+        }
+
+        let mut width = None;
+        let mut height = None;
+        let mut frame_rate = (30, 1);
+        let mut colorspace = Y4mColorspace::C420;
+        let mut full_range = false;
+
+        let invalid = |param: &str| Error::decode(format!("Некорректный параметр заголовка Y4M: {}", param));
+        for param in params {
+            let (key, value) = param.split_at_checked(1).ok_or_else(|| invalid(param))?;
+            match key {
+                "W" => width = Some(value.parse().map_err(|_| invalid(param))?),
+                "H" => height = Some(value.parse().map_err(|_| invalid(param))?),
+                "F" => {
+                    let (num, den) = value.split_once(':').ok_or_else(|| invalid(param))?;
+                    frame_rate = (
+                        num.parse().map_err(|_| invalid(param))?,
+                        den.parse().map_err(|_| invalid(param))?,
+                    );
+                }
+                "C" => colorspace = Y4mColorspace::parse(value)?,
+                "I" if value != "p" && value != "?" => {
+                    return Err(Error::decode("Чересстрочные Y4M не поддерживаются"));
+                }
+                "X" => {
+                    if let Some(range) = value.strip_prefix("COLORRANGE=") {
+                        full_range = range.eq_ignore_ascii_case("FULL");
+                    }
+                }
+                // Соотношение сторон пикселя и прочее на кадры не влияют
+                _ => {}
+            }
+        }
+
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(Error::decode("В заголовке Y4M нет размера кадра"));
+        };
+        let pixels = (width as usize).checked_mul(height as usize);
+        if width == 0 || height == 0 || pixels.is_none_or(|pixels| pixels > MAX_FRAME_PIXELS) {
+            return Err(Error::decode(format!("Недопустимый размер кадра Y4M: {}x{}", width, height)));
+        }
+        if frame_rate.0 == 0 || frame_rate.1 == 0 {
+            frame_rate = (30, 1);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            frame_rate,
+            colorspace,
+            full_range,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        let mut line = format!(
+            "{} W{} H{} F{}:{} Ip A1:1 C{}",
+            MAGIC,
+            self.width,
+            self.height,
+            self.frame_rate.0,
+            self.frame_rate.1,
+            self.colorspace.tag()
+        );
+        if self.full_range {
+            line.push_str(" XCOLORRANGE=FULL");
+        }
+        line.push('\n');
+        line
+    }
+}
+
+/// Источник кадров из файла или потока Y4M
+pub struct Y4mReader<R> {
+    reader: R,
+    header: Y4mHeader,
+    buffer: Vec<u8>,
+    frame_number: usize,
+}
+
+impl Y4mReader<BufReader<File>> {
+    pub fn open<Q: AsRef<Path>>(path: Q) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| Error::file(path, e))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> Y4mReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let line = read_line(&mut reader)?.ok_or_else(|| Error::decode("Пустой файл Y4M"))?;
+        let header = Y4mHeader::parse(&line)?;
+        let buffer = vec![0; header.colorspace.frame_len(header.width, header.height)];
+        Ok(Self {
+            reader,
+            header,
+            buffer,
+            frame_number: 0,
+        })
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+}
+
+impl<R: BufRead> VideoSource for Y4mReader<R> {
+    fn next_frame(&mut self) -> Result<Option<VideoFrame>> {
+        let Some(line) = read_line(&mut self.reader)? else {
+            return Ok(None);
+        };
+        if !line.starts_with("FRAME") {
+            return Err(Error::decode(format!("Ожидался маркер FRAME, получено: {}", line.trim_end())));
+        }
+        self.reader
+            .read_exact(&mut self.buffer)
+            .map_err(|e| Error::decode(format!("Кадр {} Y4M обрезан: {}", self.frame_number, e)))?;
+
+        let image = yuv_to_rgb(&self.buffer, &self.header);
+        let timestamp = self.frame_number as f64 * self.header.frame_rate.1 as f64 / self.header.frame_rate.0 as f64;
+        let frame = VideoFrame::new(image, self.frame_number, timestamp);
+        self.frame_number += 1;
+        Ok(Some(frame))
+    }
+}
+
+/// Приемник, записывающий кадры в Y4M. Заголовок пишется при первом
+/// кадре, когда становится известен размер.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    frame_rate: (u32, u32),
+    colorspace: Y4mColorspace,
+    full_range: bool,
+    header: Option<Y4mHeader>,
+}
+
+impl Y4mWriter<BufWriter<File>> {
+    pub fn create<Q: AsRef<Path>>(path: Q, fps: u32) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|e| Error::file(path, e))?;
+        Ok(Self::new(BufWriter::new(file), fps))
+    }
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(writer: W, fps: u32) -> Self {
+        Self {
+            writer,
+            frame_rate: (fps.max(1), 1),
+            colorspace: Y4mColorspace::default(),
+            full_range: false,
+            header: None,
+        }
+    }
+
+    /// Дробная частота кадров, например 30000:1001
+    pub fn with_frame_rate(mut self, numerator: u32, denominator: u32) -> Self {
+        self.frame_rate = (numerator.max(1), denominator.max(1));
+        self
+    }
+
+    pub fn with_colorspace(mut self, colorspace: Y4mColorspace) -> Self {
+        self.colorspace = colorspace;
+        self
+    }
+
+    /// Писать полный диапазон 0..255 (`XCOLORRANGE=FULL`)
+    pub fn with_full_range(mut self) -> Self {
+        self.full_range = true;
+        self
+    }
+
+    /// Завершает запись и возвращает внутренний writer
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> VideoSink for Y4mWriter<W> {
+    fn write_frame(&mut self, frame: &VideoFrame) -> Result<()> {
+        let header = match &self.header {
+            Some(header) => {
+                if (header.width, header.height) != frame.dimensions() {
+                    return Err(Error::DimensionMismatch {
+                        expected: (header.width, header.height),
+                        actual: frame.dimensions(),
+                    });
+                }
+                header
+            }
+            None => {
+                let header = Y4mHeader {
+                    width: frame.width(),
+                    height: frame.height(),
+                    frame_rate: self.frame_rate,
+                    colorspace: self.colorspace,
+                    full_range: self.full_range,
+                };
+                self.writer.write_all(header.to_line().as_bytes())?;
+                self.header.insert(header)
+            }
+        };
+
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&rgb_to_yuv(&frame.data, header))?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Читает строку заголовка до `\n`; `None` - конец потока
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&line).trim_end_matches('\n').to_string()))
+}
+
+/// Коэффициенты BT.601 с учетом диапазона: (смещение Y, масштаб Y, масштаб цветности)
+fn range_params(full_range: bool) -> (f32, f32, f32) {
+    if full_range {
+        (0.0, 1.0, 1.0)
+    } else {
+        (16.0, 255.0 / 219.0, 255.0 / 224.0)
+    }
+}
+
+fn yuv_to_rgb(data: &[u8], header: &Y4mHeader) -> RgbImage {
+    let (width, height) = (header.width, header.height);
+    let luma_len = width as usize * height as usize;
+    let (y_offset, y_scale, c_scale) = range_params(header.full_range);
+    let chroma = header.colorspace.chroma_size(width, height);
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let luma = (data[y as usize * width as usize + x as usize] as f32 - y_offset) * y_scale;
+        let (cb, cr) = match chroma {
+            Some((chroma_width, chroma_height)) => {
+                // Ближайший отсчет цветности
+                let cx = x as usize * chroma_width as usize / width as usize;
+                let cy = y as usize * chroma_height as usize / height as usize;
+                let index = luma_len + cy * chroma_width as usize + cx;
+                let plane = chroma_width as usize * chroma_height as usize;
+                (
+                    (data[index] as f32 - 128.0) * c_scale,
+                    (data[index + plane] as f32 - 128.0) * c_scale,
+                )
+            }
+            None => (0.0, 0.0),
+        };
+
+        let to_u8 = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+        Rgb([
+            to_u8(luma + 1.402 * cr),
+            to_u8(luma - 0.344_136 * cb - 0.714_136 * cr),
+            to_u8(luma + 1.772 * cb),
+        ])
+    })
+}
+
+fn rgb_to_yuv(image: &RgbImage, header: &Y4mHeader) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let (y_offset, y_scale, c_scale) = range_params(header.full_range);
+    let to_u8 = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+
+    let mut out = Vec::with_capacity(header.colorspace.frame_len(width, height));
+    for pixel in image.pixels() {
+        let [r, g, b] = pixel.0.map(|c| c as f32);
+        let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+        out.push(to_u8(luma / y_scale + y_offset));
+    }
+
+    let Some((chroma_width, chroma_height)) = header.colorspace.chroma_size(width, height) else {
+        return out;
+    };
+    let (step_x, step_y) = (width.div_ceil(chroma_width), height.div_ceil(chroma_height));
+
+    // Усредняем блок пикселей, попадающих в отсчет цветности
+    let mut cb_plane = Vec::with_capacity(chroma_width as usize * chroma_height as usize);
+    let mut cr_plane = Vec::with_capacity(cb_plane.capacity());
+    for cy in 0..chroma_height {
+        for cx in 0..chroma_width {
+            let (mut cb, mut cr, mut count) = (0.0, 0.0, 0.0);
+            for y in cy * step_y..((cy + 1) * step_y).min(height) {
+                for x in cx * step_x..((cx + 1) * step_x).min(width) {
+                    let [r, g, b] = image.get_pixel(x, y).0.map(|c| c as f32);
+                    cb += -0.168_736 * r - 0.331_264 * g + 0.5 * b;
+                    cr += 0.5 * r - 0.418_688 * g - 0.081_312 * b;
+                    count += 1.0;
+                }
+            }
+            cb_plane.push(to_u8(cb / count / c_scale + 128.0));
+            cr_plane.push(to_u8(cr / count / c_scale + 128.0));
+        }
+    }
+
+    out.extend(cb_plane);
+    out.extend(cr_plane);
+    out
+}